@@ -1,25 +1,146 @@
-
 use std::env;
 #[path = "../../utils/src/trails.rs"] mod trails;
-use trails::TrailFinder;
+use trails::{ClimbingRules, Coordinates, TrailFinder};
+#[path = "../../utils/src/args.rs"] mod args;
+use args::flag_value;
+
+const USAGE: &str = "usage: day10 [file] [--trail-head <row,col>] [--limit N] [--render all|N]
+             [--delta <min>..<max>] [--start <height>] [--goal <height>] [--diagonal]";
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
 
-    let mut trail_finder: TrailFinder = TrailFinder::new();
+    let cmd_args: Vec<String> = env::args().collect();
 
-    trail_finder.get_from_file(env::args());
+    let mut file_name = "example".to_string();
+    let mut trail_head: Option<Coordinates> = None;
+    let mut limit: Option<usize> = None;
+    let mut render: Option<String> = None;
+    let mut rules = ClimbingRules::default();
 
-    trail_finder.find_all_trail_head_peaks();
+    // --trail-head lists the trails from one trail head, --render all draws every trail (from the trail head if one is
+    // given) over the map and --render N draws just the Nth trail from the trail head
+    let mut i = 1;
+    while i < cmd_args.len() {
+        match cmd_args[i].as_str() {
+            "--trail-head" => {
+                let Some(coordinates) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                trail_head = parse_coordinates(coordinates);
+                if trail_head.is_none() {
+                    println!("trail head should be row,col not {}", coordinates);
+                    return;
+                }
+            },
+            "--limit" => {
+                let Some(count) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let Ok(count) = count.trim().parse() else {
+                    println!("limit should be a number not {}", count);
+                    return;
+                };
+                limit = Some(count);
+            },
+            "--render" => {
+                let Some(which) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                render = Some(which.to_string());
+            },
+            "--delta" => {
+                // a single number or a range like 1..3
+                let Some(delta) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let Some((min_delta, max_delta)) = parse_delta(delta) else {
+                    println!("height delta should be N or min..max not {}", delta);
+                    return;
+                };
+                rules.min_delta = min_delta;
+                rules.max_delta = max_delta;
+            },
+            "--start" => {
+                let Some(height) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let Ok(start_height) = height.trim().parse() else {
+                    println!("start height should be a number not {}", height);
+                    return;
+                };
+                rules.start_height = start_height;
+            },
+            "--goal" => {
+                let Some(height) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let Ok(goal_height) = height.trim().parse() else {
+                    println!("goal height should be a number not {}", height);
+                    return;
+                };
+                rules.goal_height = goal_height;
+            },
+            "--diagonal" => rules.diagonals = true,
+            arg => file_name = arg.to_string(),
+        }
+        i += 1;
+    }
 
-    trail_finder.print();
+    if limit.is_some() && trail_head.is_none() {
+        println!("--limit only applies to the trails listed for a --trail-head, ignoring it");
+    }
 
-    
-}
+    let mut trail_finder: TrailFinder = TrailFinder::new();
 
+    if let Err(e) = trail_finder.set_rules(rules) {
+        println!("{}", e);
+        return;
+    }
 
+    trail_finder.read_file(&file_name);
 
+    if let Some(trail_head) = trail_head {
+        if !trail_finder.is_trail_head(&trail_head) {
+            println!("{} is not a trail head on the map", trail_head);
+            return;
+        }
+    }
 
+    trail_finder.find_all_trail_head_peaks();
+
+    trail_finder.print();
 
+    if let Some(trail_head) = trail_head {
+        let trails = trail_finder.trails(&trail_head).take(limit.unwrap_or(usize::MAX));
+        for (number, trail) in trails.enumerate() {
+            let steps: Vec<String> = trail.iter().map(|coord| coord.to_string()).collect();
+            println!("trail {}: {}", number + 1, steps.join(" -> "));
+        }
+    }
 
+    match render.as_deref() {
+        None => {},
+        Some("all") => {
+            let trail_heads = match trail_head {
+                Some(trail_head) => vec![trail_head],
+                None => trail_finder.trail_heads(),
+            };
+            print!("{}", trail_finder.render(&trail_finder.trail_cells(&trail_heads)));
+        },
+        Some(number) => {
+            // trails are numbered from 1, the same as when they are listed
+            let (Some(trail_head), Some(number)) = (trail_head, number.parse::<usize>().ok().filter(|number| *number > 0)) else {
+                println!("--render N needs a trail number and a --trail-head");
+                return;
+            };
+            match trail_finder.trails(&trail_head).nth(number - 1) {
+                Some(trail) => print!("{}", trail_finder.render(&trail.into_iter().collect())),
+                None => println!("trail head {} doesn't have a trail {}", trail_head, number),
+            }
+        },
+    }
+}
 
+fn parse_coordinates(s: &str) -> Option<Coordinates> {
+    let (row, col) = s.split_once(',')?;
+    Some(Coordinates::new(row.trim().parse().ok()?, col.trim().parse().ok()?))
+}
+
+fn parse_delta(s: &str) -> Option<(i32, i32)> {
+    match s.split_once("..") {
+        Some((min_delta, max_delta)) => Some((min_delta.trim().parse().ok()?, max_delta.trim().parse().ok()?)),
+        None => {
+            let delta = s.trim().parse().ok()?;
+            Some((delta, delta))
+        },
+    }
+}
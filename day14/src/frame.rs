@@ -0,0 +1,219 @@
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum ImageFormat {
+    Ppm,
+    #[cfg(feature = "png")]
+    Png,
+    #[cfg(feature = "gif")]
+    Gif,
+}
+
+impl ImageFormat {
+    pub fn parse(s: &str) -> Option<ImageFormat> {
+        match s {
+            "ppm" => Some(ImageFormat::Ppm),
+            #[cfg(feature = "png")]
+            "png" => Some(ImageFormat::Png),
+            #[cfg(feature = "gif")]
+            "gif" => Some(ImageFormat::Gif),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<[u8; 3]>) -> Palette {
+        assert!(!colors.is_empty() && colors.len() <= 256, "palette needs 1 to 256 colors");
+        Palette { colors }
+    }
+
+    // index 0 is an empty cell, every following index is one more robot in the cell
+    pub fn robots() -> Palette {
+        Palette::new(vec![[16, 16, 24], [40, 160, 60], [120, 220, 90], [240, 240, 120]])
+    }
+
+    // black through red and yellow up to white, for scaling values between 0 and a maximum
+    pub fn heat() -> Palette {
+        let mut colors = vec![];
+        for i in 0..=255u32 {
+            let red = (i * 3).min(255) as u8;
+            let green = (i * 3).saturating_sub(255).min(255) as u8;
+            let blue = (i * 3).saturating_sub(510).min(255) as u8;
+            colors.push([red, green, blue]);
+        }
+        Palette::new(colors)
+    }
+
+    pub fn index_for(&self, count: i32) -> u8 {
+        count.clamp(0, self.colors.len() as i32 - 1) as u8
+    }
+
+    pub fn color(&self, index: u8) -> [u8; 3] {
+        self.colors[index as usize]
+    }
+
+    #[cfg(any(feature = "png", feature = "gif"))]
+    fn as_rgb_bytes(&self) -> Vec<u8> {
+        self.colors.iter().flatten().copied().collect()
+    }
+}
+
+// an image where every grid cell is drawn as a `scale` x `scale` block of one palette color
+pub struct Frame {
+    width: usize,
+    height: usize,
+    indexes: Vec<u8>,
+    palette: Palette,
+}
+
+impl Frame {
+    pub fn from_counts(counts: &[Vec<i32>], scale: usize, palette: &Palette) -> Frame {
+        let rows = counts.len();
+        let cols = if rows > 0 { counts[0].len() } else { 0 };
+        let width = cols * scale;
+        let height = rows * scale;
+
+        let mut indexes = vec![0u8; width * height];
+
+        for (row, cells) in counts.iter().enumerate() {
+            for (col, count) in cells.iter().enumerate() {
+                let index = palette.index_for(*count);
+                for y in row * scale..(row + 1) * scale {
+                    let start = y * width + col * scale;
+                    indexes[start..start + scale].fill(index);
+                }
+            }
+        }
+
+        Frame { width, height, indexes, palette: palette.clone() }
+    }
+
+    // scales every value against the largest one so the whole palette gets used
+    pub fn from_heat(values: &[Vec<u64>], scale: usize, palette: &Palette) -> Frame {
+        let max = values.iter().flatten().copied().max().unwrap_or(0).max(1);
+        let top_index = palette.colors.len() as u64 - 1;
+
+        let scaled: Vec<Vec<i32>> = values.iter()
+            .map(|row| row.iter().map(|value| (value * top_index / max) as i32).collect())
+            .collect();
+
+        Frame::from_counts(&scaled, scale, palette)
+    }
+
+    pub fn write(&self, dir: &Path, name: &str, format: ImageFormat) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => self.write_ppm(&dir.join(format!("{}.ppm", name))),
+            #[cfg(feature = "png")]
+            ImageFormat::Png => self.write_png(&dir.join(format!("{}.png", name))),
+            #[cfg(feature = "gif")]
+            ImageFormat::Gif => self.write_gif(&dir.join(format!("{}.gif", name))),
+        }
+    }
+
+    fn rgb(&self) -> Vec<u8> {
+        let mut rgb = Vec::with_capacity(self.indexes.len() * 3);
+        for index in &self.indexes {
+            rgb.extend_from_slice(&self.palette.color(*index));
+        }
+        rgb
+    }
+
+    // binary PPM (P6), readable by most image viewers without any extra crates
+    pub fn write_ppm(&self, path: &Path) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+        writer.write_all(&self.rgb())?;
+        writer.flush()
+    }
+
+    #[cfg(feature = "png")]
+    pub fn write_png(&self, path: &Path) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Indexed);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_palette(self.palette.as_rgb_bytes());
+
+        let mut png_writer = encoder.write_header().map_err(io::Error::other)?;
+        png_writer.write_image_data(&self.indexes).map_err(io::Error::other)
+    }
+
+    #[cfg(feature = "gif")]
+    pub fn write_gif(&self, path: &Path) -> io::Result<()> {
+        let width = u16::try_from(self.width).map_err(io::Error::other)?;
+        let height = u16::try_from(self.height).map_err(io::Error::other)?;
+
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = gif::Encoder::new(writer, width, height, &self.palette.as_rgb_bytes()).map_err(io::Error::other)?;
+        encoder.write_frame(&self.gif_frame(width, height)).map_err(io::Error::other)
+    }
+
+    #[cfg(feature = "gif")]
+    fn gif_frame(&self, width: u16, height: u16) -> gif::Frame<'_> {
+        gif::Frame {
+            width,
+            height,
+            delay: 10,
+            buffer: std::borrow::Cow::Borrowed(&self.indexes),
+            ..gif::Frame::default()
+        }
+    }
+}
+
+// writes one image per recorded frame into a directory, or a single animation for gif
+pub struct FrameRecorder {
+    dir: PathBuf,
+    format: ImageFormat,
+    scale: usize,
+    palette: Palette,
+    #[cfg(feature = "gif")]
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl FrameRecorder {
+    pub fn new(dir: &str, format: ImageFormat, scale: usize) -> io::Result<FrameRecorder> {
+        fs::create_dir_all(dir)?;
+
+        Ok(FrameRecorder {
+            dir: PathBuf::from(dir),
+            format,
+            scale,
+            palette: Palette::robots(),
+            #[cfg(feature = "gif")]
+            gif: None,
+        })
+    }
+
+    pub fn record(&mut self, iteration: i64, counts: &[Vec<i32>]) -> io::Result<()> {
+        let frame = Frame::from_counts(counts, self.scale, &self.palette);
+
+        match self.format {
+            #[cfg(feature = "gif")]
+            ImageFormat::Gif => self.record_gif(&frame),
+            _ => frame.write(&self.dir, &format!("frame_{:06}", iteration), self.format),
+        }
+    }
+
+    #[cfg(feature = "gif")]
+    fn record_gif(&mut self, frame: &Frame) -> io::Result<()> {
+        let width = u16::try_from(frame.width).map_err(io::Error::other)?;
+        let height = u16::try_from(frame.height).map_err(io::Error::other)?;
+
+        if self.gif.is_none() {
+            let writer = BufWriter::new(File::create(self.dir.join("robots.gif"))?);
+            let mut encoder = gif::Encoder::new(writer, width, height, &self.palette.as_rgb_bytes()).map_err(io::Error::other)?;
+            encoder.set_repeat(gif::Repeat::Infinite).map_err(io::Error::other)?;
+            self.gif = Some(encoder);
+        }
+
+        self.gif.as_mut().unwrap().write_frame(&frame.gif_frame(width, height)).map_err(io::Error::other)
+    }
+}
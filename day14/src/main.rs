@@ -2,7 +2,13 @@ use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 use std::env;
-use std::{thread, time};
+
+#[path = "../../utils/src/args.rs"] mod args;
+use args::flag_value;
+mod frame;
+use frame::{Frame, FrameRecorder, ImageFormat, Palette};
+mod stats;
+use stats::{Metric, StepStats};
 
 #[derive(Copy, Clone, Eq, Hash, PartialEq)]
 struct Coordinates {
@@ -41,32 +47,53 @@ impl Robot {
         Robot {start_pos: new_pos, current_pos: new_pos, velocity: new_velocity}
     }
 
-    fn advance(&mut self, num_times_to_advance: i32, x_max: i32, y_max: i32) {
-        self.current_pos.x = (self.current_pos.x + self.velocity.x  * num_times_to_advance) % x_max;
-        self.current_pos.y = (self.current_pos.y + self.velocity.y  * num_times_to_advance) % y_max;
-
-        if self.current_pos.x < 0 {
-            self.current_pos.x += x_max;
-        }
-        if self.current_pos.y < 0 {
-            self.current_pos.y += y_max;
-        }
+    // each axis wraps independently, so the position at any time is just start + velocity * time modulo the arena size
+    fn position_at(&self, time: i64, x_max: i32, y_max: i32) -> Coordinates {
+        Coordinates::new(axis_position_at(self.start_pos.x, self.velocity.x, time, x_max),
+                        axis_position_at(self.start_pos.y, self.velocity.y, time, y_max))
     }
 
 }
 
+fn axis_position_at(start: i32, velocity: i32, time: i64, max: i32) -> i32 {
+    let max = max as i64;
+    // reduce the time first so huge times cannot overflow the multiplication
+    let steps = time.rem_euclid(max);
+    (start as i64 + velocity as i64 * steps).rem_euclid(max) as i32
+}
+
+// two or more robots standing in the same cell at the same time
+struct Collision {
+    time: i64,
+    pos: Coordinates,
+    robot_count: i32,
+}
+
 struct Arena {
-    height: u8,
-    width: u8,
+    height: i32,
+    width: i32,
     robots: Vec<Robot>,
 }
 
-use std::fs::OpenOptions;
+use std::fs;
 use std::io::prelude::*;
+use std::io::BufWriter;
 
 impl Arena {
 
-    fn print_to_file(&self, iteration: i32, file: &mut File) {
+    // number of robots in each cell, indexed by [y][x]
+    fn cell_counts(&self) -> Vec<Vec<i32>> {
+        let mut map: Vec<Vec<i32>> = vec![vec![0; self.width as usize]; self.height as usize];
+
+        for robot in &self.robots {
+            map[robot.current_pos.y as usize][robot.current_pos.x as usize] += 1;
+        }
+
+        map
+    }
+
+    #[allow(dead_code)]
+    fn print_to_file(&self, iteration: i64, file: &mut File) {
         if let Err(e) = writeln!(file, "==========================================================================================================================") {
             eprintln!("Couldn't write to file: {}", e);
         }
@@ -77,17 +104,7 @@ impl Arena {
         }
 
 
-        let mut map: Vec<Vec<i32>> = vec![];
-        for _ in 0..self.height {
-            //let row: Vec<i32> = vec![];
-            map.push(vec![0; self.width as usize]);
-        }
-
-        for robot in &self.robots {
-            map[robot.current_pos.y as usize][robot.current_pos.x as usize] += 1;
-        }
-
-        for row in map {
+        for row in self.cell_counts() {
             for col in row {
                 if col == 0 {
                     if let Err(e) = write!(file, ".") {
@@ -106,22 +123,12 @@ impl Arena {
         }
     }
 
-    fn print(&self, iteration: i32) {
+    fn print(&self, iteration: i64) {
         println!("==========================================================================================================================");
         print!("\x1B[2J\x1B[1;1H");
         println!("after {} seconds", iteration);
 
-        let mut map: Vec<Vec<i32>> = vec![];
-        for _ in 0..self.height {
-            //let row: Vec<i32> = vec![];
-            map.push(vec![0; self.width as usize]);
-        }
-
-        for robot in &self.robots {
-            map[robot.current_pos.y as usize][robot.current_pos.x as usize] += 1;
-        }
-
-        for row in map {
+        for row in self.cell_counts() {
             for col in row {
                 if col == 0 {
                     print!(".");
@@ -139,75 +146,240 @@ impl Arena {
     
 
 
-    fn advance_robots(&mut self, num_times_to_advance: i32) {
-        /* 
-        let mut file = OpenOptions::new()
-        .write(true)
-        .append(true)
-        .open("tree")
-        .unwrap();
-        */
+    fn set_time(&mut self, time: i64) {
+        let (x_max, y_max) = (self.width, self.height);
+        for robot in &mut self.robots {
+            robot.current_pos = robot.position_at(time, x_max, y_max);
+        }
+    }
 
-        for i in 0..num_times_to_advance {
-            for robot in &mut self.robots {
-                robot.advance(1, self.width.into(), self.height.into());
-            }
+    fn advance_robots(&mut self, num_times_to_advance: i64, recorder: &mut Option<FrameRecorder>) {
+        let Some(recorder) = recorder else {
+            // nothing needs the frames in between, so jump straight to the end
+            self.set_time(num_times_to_advance);
+            return;
+        };
 
-            //print!("\x1B[2J\x1B[1;1H");
-            //println!("after {} seconds", i);
-            //self.print_to_file(i, &mut file);
-            //self.print(i);
-            //thread::sleep(time::Duration::from_millis(300));
+        for i in 1..=num_times_to_advance {
+            self.set_time(i);
+
+            if let Err(e) = recorder.record(i, &self.cell_counts()) {
+                eprintln!("Couldn't write frame {}: {}", i, e);
+            }
         }
-        
     }
 
-    fn safety_factor(&self) -> u32 {
-        let mid_height: i32 = (self.height / 2).into();
-        let mid_width: i32 = (self.width / 2).into();
+    // x positions repeat every width seconds and y positions every height seconds,
+    // so the whole arrangement repeats every lcm(width, height) seconds
+    fn period(&self) -> i64 {
+        let (width, height) = (self.width as i64, self.height as i64);
+        width / gcd(width, height) * height
+    }
+
+    // the robots repeat their arrangement every period, so only that many steps need checking
+    fn find_easter_egg(&mut self, metric: Metric) -> (i64, StepStats) {
+        let mut best_step = 0;
+        let mut best_stats: Option<StepStats> = None;
 
-        println!("mid_width {} mid_height {}", mid_width, mid_height);
-        
-        let mut sum0: u32 = 0;
-        let mut sum1: u32 = 0;
-        let mut sum2: u32 = 0;
-        let mut sum3: u32 = 0;
+        for step in 0..self.period() {
+            self.set_time(step);
+
+            let stats = StepStats::from_counts(&self.cell_counts());
+            if best_stats.is_none_or(|best| stats.is_better_than(&best, metric)) {
+                best_step = step;
+                best_stats = Some(stats);
+            }
+        }
+
+        // leave the robots in the arrangement that was found
+        self.set_time(best_step);
+
+        (best_step, best_stats.unwrap())
+    }
+
+    fn axis_variance(&self, time: i64, max: i32, axis: fn(&Robot) -> (i32, i32)) -> f64 {
+        let n = self.robots.len() as f64;
+        let mut sum = 0.0;
+        let mut sum_squares = 0.0;
 
         for robot in &self.robots {
-            
-            // println!("start {},{} velocity {},{}", robot.start_pos.x, robot.start_pos.y, robot.velocity.x, robot.velocity.y);
-            // println!("now   {},{}", robot.current_pos.x, robot.current_pos.y);
+            let (start, velocity) = axis(robot);
+            let position = axis_position_at(start, velocity, time, max) as f64;
+            sum += position;
+            sum_squares += position * position;
+        }
 
-            if robot.current_pos.x == mid_width || robot.current_pos.y == mid_height {
-                // robot is in the middle so don't count it
-                println!("not counted");
-                continue;
+        let mean = sum / n;
+        sum_squares / n - mean * mean
+    }
+
+    fn tightest_axis_time(&self, max: i32, axis: fn(&Robot) -> (i32, i32)) -> i64 {
+        let mut best_time = 0;
+        let mut best_variance = f64::MAX;
+
+        for time in 0..max as i64 {
+            let variance = self.axis_variance(time, max, axis);
+            if variance < best_variance {
+                best_time = time;
+                best_variance = variance;
             }
+        }
+
+        best_time
+    }
 
-            // if robot.start_pos.x != 2 || robot.start_pos.y != 4 {
-            //     continue;
-            // }
+    /*
+    find the x offset (mod width) and the y offset (mod height) where each axis is most bunched together,
+    then combine them with the Chinese Remainder Theorem into the single time where both happen at once
+     */
+    fn find_clustering_time(&self) -> Option<i64> {
+        let x_time = self.tightest_axis_time(self.width, |robot| (robot.start_pos.x, robot.velocity.x));
+        let y_time = self.tightest_axis_time(self.height, |robot| (robot.start_pos.y, robot.velocity.y));
 
+        println!("x clusters at {} mod {}, y clusters at {} mod {}", x_time, self.width, y_time, self.height);
 
-            
-            if robot.current_pos.x < mid_width && robot.current_pos.y < mid_height {
-                sum0 += 1;
-            } else if robot.current_pos.x > mid_width && robot.current_pos.y < mid_height {
-                sum1 += 1;
-            } else if robot.current_pos.x < mid_width && robot.current_pos.y > mid_height {
-                sum2 += 1;
-            } else if robot.current_pos.x > mid_width && robot.current_pos.y > mid_height {
-                sum3 += 1;
+        chinese_remainder(x_time, self.width as i64, y_time, self.height as i64)
+    }
+
+    // how many robot-seconds were spent in each cell between start and end (exclusive), and every time robots shared a cell
+    fn heat_map(&mut self, start: i64, end: i64) -> (Vec<Vec<u64>>, Vec<Collision>) {
+        let mut heat: Vec<Vec<u64>> = vec![vec![0; self.width as usize]; self.height as usize];
+        let mut collisions: Vec<Collision> = vec![];
+
+        for time in start..end {
+            self.set_time(time);
+
+            for (y, row) in self.cell_counts().iter().enumerate() {
+                for (x, count) in row.iter().enumerate() {
+                    heat[y][x] += *count as u64;
+
+                    if *count > 1 {
+                        collisions.push(Collision { time, pos: Coordinates::new(x as i32, y as i32), robot_count: *count });
+                    }
+                }
             }
         }
 
-        println!("{} {} {} {}", sum0, sum1, sum2, sum3);
+        (heat, collisions)
+    }
+
+    fn write_heat_map(&mut self, start: i64, end: i64, dir: &str, format: ImageFormat, scale: usize) -> io::Result<()> {
+        let (heat, collisions) = self.heat_map(start, end);
 
-        sum0 * sum1 * sum2 * sum3
+        fs::create_dir_all(dir)?;
+        let dir = Path::new(dir);
+
+        let mut csv = BufWriter::new(File::create(dir.join("heatmap.csv"))?);
+        for row in &heat {
+            let row_strings: Vec<String> = row.iter().map(|value| value.to_string()).collect();
+            writeln!(csv, "{}", row_strings.join(","))?;
+        }
+        csv.flush()?;
+
+        Frame::from_heat(&heat, scale, &Palette::heat()).write(dir, "heatmap", format)?;
+
+        let mut log = BufWriter::new(File::create(dir.join("collisions.log"))?);
+        for collision in &collisions {
+            writeln!(log, "after {} seconds {} robots at {},{}", collision.time, collision.robot_count, collision.pos.x, collision.pos.y)?;
+        }
+        log.flush()?;
+
+        let mut collision_times: Vec<i64> = collisions.iter().map(|collision| collision.time).collect();
+        collision_times.dedup();
+        println!("{} collisions over {} of the {} seconds from {} to {}",
+            collisions.len(), collision_times.len(), end - start, start, end);
+
+        Ok(())
     }
 
-    
+    /*
+    split the arena into cols x rows sections and count the robots in each one.
+    when the sections fit exactly with a one cell gap between them (like the middle row and column
+    of the 2x2 quadrants) robots standing in the gaps are not counted, otherwise the arena is split
+    as evenly as possible with no gaps
+     */
+    fn partition_counts(&self, cols: i32, rows: i32) -> Vec<Vec<u64>> {
+        let mut counts = vec![vec![0u64; cols as usize]; rows as usize];
 
+        for robot in &self.robots {
+            let section_x = partition_index(robot.current_pos.x, self.width, cols);
+            let section_y = partition_index(robot.current_pos.y, self.height, rows);
+
+            if let (Some(section_x), Some(section_y)) = (section_x, section_y) {
+                counts[section_y][section_x] += 1;
+            }
+        }
+
+        counts
+    }
+
+    fn safety_factor(&self, cols: i32, rows: i32) -> u64 {
+        let counts = self.partition_counts(cols, rows);
+
+        for row in &counts {
+            let row_strings: Vec<String> = row.iter().map(|count| count.to_string()).collect();
+            println!("{}", row_strings.join(" "));
+        }
+
+        counts.iter().flatten().product()
+    }
+
+}
+
+// which of the `parts` sections along an axis of `length` cells a position is in, None when it is in a gap
+fn partition_index(position: i32, length: i32, parts: i32) -> Option<usize> {
+    let gaps = parts - 1;
+
+    if length > gaps && (length - gaps) % parts == 0 {
+        let section_length = (length - gaps) / parts;
+        if (position + 1) % (section_length + 1) == 0 {
+            // on one of the dividing lines
+            return None;
+        }
+        return Some((position / (section_length + 1)) as usize);
+    }
+
+    Some((position as i64 * parts as i64 / length as i64) as usize)
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// returns (g, x, y) such that a * x + b * y = g = gcd(a, b)
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = extended_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
+}
+
+// smallest non-negative t with t = a (mod m) and t = b (mod n), if one exists
+fn chinese_remainder(a: i64, m: i64, b: i64, n: i64) -> Option<i64> {
+    let (g, p, _) = extended_gcd(m, n);
+    if (b - a) % g != 0 {
+        return None;
+    }
+
+    let lcm = m / g * n;
+    let k = ((b - a) / g % (n / g) * p).rem_euclid(n / g);
+    Some((a + m * k).rem_euclid(lcm))
+}
+
+fn parse_i64(s: &str) -> i64 {
+    match s.parse::<i64>() {
+        Ok(n) => n,
+        Err(_e) => {
+            println!("{}", s);
+            panic!();
+        },
+    }
 }
 
 fn parse_i32(s: &str) -> i32 {
@@ -223,34 +395,125 @@ fn parse_i32(s: &str) -> i32 {
     }
 }
 
+const USAGE: &str = "usage: day14 [file] [seconds] [--frames <dir>] [--format ppm|png|gif] [--scale <pixels per cell>]
+             [--find-egg variance|entropy|cluster|neighbours] [--crt]
+             [--width <cells>] [--height <cells>] [--partition <cols>x<rows>]
+             [--heatmap <start>..<end>] [--heatmap-dir <dir>]";
+
 fn main() {
     let mut file_name: &str = "example";
+    let mut num_times_to_advance: i64 = 5;
 
-    let cmd_args: Vec<String> = env::args().collect();
+    let mut frames_dir: Option<&str> = None;
+    let mut frame_format = ImageFormat::Ppm;
+    let mut frame_scale: usize = 4;
 
-    if cmd_args.len() > 1 {
-        file_name = &cmd_args[1];
-    }
+    let mut easter_egg_metric: Option<Metric> = None;
+    let mut find_clustering_time = false;
 
-    let mut num_times_to_advance: i32 = 5;
+    let mut width_arg: Option<i32> = None;
+    let mut height_arg: Option<i32> = None;
+    let mut partition: (i32, i32) = (2, 2);
 
+    let mut heat_map_window: Option<(i64, i64)> = None;
+    let mut heat_map_dir: &str = "heatmap";
 
-    if cmd_args.len() > 2 {
-        num_times_to_advance = parse_i32(&cmd_args[2]);
-    }
+    let cmd_args: Vec<String> = env::args().collect();
 
-    // default is example, so use these dimensions
-    let mut arena = Arena { width: 11, height: 7, robots: vec![] };
-    
-    if file_name == "input" {
-        arena.width = 101;
-        arena.height = 103;
+    let mut positional = 0;
+    let mut i = 1;
+    while i < cmd_args.len() {
+        match cmd_args[i].as_str() {
+            "--frames" => {
+                let Some(dir) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                frames_dir = Some(dir);
+            },
+            "--format" => {
+                let Some(format) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                frame_format = ImageFormat::parse(format).unwrap_or_else(|| {
+                    panic!("unsupported frame format {} (png and gif need the matching cargo feature)", format)
+                });
+            },
+            "--scale" => {
+                let Some(scale) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let scale = parse_i32(scale);
+                if scale < 1 {
+                    println!("--scale needs at least 1 pixel per cell not {}", scale);
+                    return;
+                }
+                frame_scale = scale as usize;
+            },
+            "--width" => {
+                let Some(width) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                width_arg = Some(parse_i32(width));
+            },
+            "--height" => {
+                let Some(height) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                height_arg = Some(parse_i32(height));
+            },
+            "--partition" => {
+                let Some(sections) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let sections: Vec<&str> = sections.split("x").collect();
+                assert!(sections.len() == 2, "partition should look like 2x2");
+                partition = (parse_i32(sections[0]), parse_i32(sections[1]));
+                assert!(partition.0 > 0 && partition.1 > 0, "partition needs at least one section each way");
+            },
+            "--heatmap" => {
+                let Some(window) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                let window: Vec<&str> = window.split("..").collect();
+                assert!(window.len() == 2, "heat map window should look like 0..100");
+                heat_map_window = Some((parse_i64(window[0]), parse_i64(window[1])));
+            },
+            "--heatmap-dir" => {
+                let Some(dir) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                heat_map_dir = dir;
+            },
+            "--crt" => {
+                find_clustering_time = true;
+            },
+            "--find-egg" => {
+                let Some(metric) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                easter_egg_metric = Some(Metric::parse(metric).unwrap_or_else(|| {
+                    panic!("unknown metric {}", metric)
+                }));
+            },
+            arg => {
+                match positional {
+                    0 => file_name = arg,
+                    1 => num_times_to_advance = parse_i64(arg),
+                    _ => panic!("unexpected argument {}", arg),
+                }
+                positional += 1;
+            },
+        }
+        i += 1;
     }
 
-    if let Ok(lines) = read_lines(file_name.to_string()) {
+    let mut arena = Arena { width: 0, height: 0, robots: vec![] };
+    let mut header_size: Option<(i32, i32)> = None;
+
+    let mut recorder: Option<FrameRecorder> = frames_dir.map(|dir| {
+        FrameRecorder::new(dir, frame_format, frame_scale).expect("Error creating frame directory")
+    });
+
+    if let Ok(lines) = read_lines(file_name) {
         // Consumes the iterator, returns an (Optional) String
 
-        for line in lines.flatten() {
+        for line in lines.map_while(Result::ok) {
+            /*
+            optional header line giving the arena size as width,height:
+            size=101,103
+             */
+            if let Some(size_str) = line.strip_prefix("size=") {
+                let size: Vec<&str> = size_str.split(",").collect();
+                if size.len() != 2 {
+                    println!("size header should look like size=101,103 not {}", line);
+                    return;
+                }
+                header_size = Some((parse_i32(size[0]), parse_i32(size[1])));
+                continue;
+            }
+
             /*
             line in the form of:
             p=0,4 v=3,-3
@@ -267,10 +530,51 @@ fn main() {
 
         }
 
+        // command line flags win over the header, and without either the size depends on which puzzle file this is
+        let default_size = header_size.unwrap_or(if file_name == "input" { (101, 103) } else { (11, 7) });
+        arena.width = width_arg.unwrap_or(default_size.0);
+        arena.height = height_arg.unwrap_or(default_size.1);
+        assert!(arena.width > 0 && arena.height > 0, "arena needs a positive width and height");
+        println!("arena {}x{}", arena.width, arena.height);
+
+
+        if let Some((start, end)) = heat_map_window {
+            if let Err(e) = arena.write_heat_map(start, end, heat_map_dir, frame_format, frame_scale) {
+                eprintln!("Couldn't write heat map: {}", e);
+            }
+            return;
+        }
+
+        if find_clustering_time {
+            match arena.find_clustering_time() {
+                Some(time) => {
+                    arena.set_time(time);
+                    arena.print(time);
+                    println!("robots cluster after {} seconds", time);
+                },
+                None => println!("the x and y clustering times never line up"),
+            }
+            return;
+        }
+
+        if let Some(metric) = easter_egg_metric {
+            let (step, stats) = arena.find_easter_egg(metric);
+
+            arena.print(step);
+            if let Some(recorder) = &mut recorder {
+                if let Err(e) = recorder.record(step, &arena.cell_counts()) {
+                    eprintln!("Couldn't write frame {}: {}", step, e);
+                }
+            }
+
+            let direction = if metric.minimise() { "lowest" } else { "highest" };
+            println!("{} {} after {} seconds: {}", direction, metric, step, stats);
+            return;
+        }
 
-        arena.advance_robots(num_times_to_advance);
+        arena.advance_robots(num_times_to_advance, &mut recorder);
         
-        println!("safety factor after {} seconds: {}", num_times_to_advance, arena.safety_factor());
+        println!("safety factor after {} seconds: {}", num_times_to_advance, arena.safety_factor(partition.0, partition.1));
     } else {
         panic!();
     }
@@ -0,0 +1,238 @@
+use std::collections::VecDeque;
+use std::fmt;
+
+// robots are grouped into blocks of this many cells per side when measuring entropy,
+// otherwise almost every robot sits in its own cell and every frame looks equally random
+const ENTROPY_BLOCK_SIZE: usize = 5;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum Metric {
+    Variance,
+    Entropy,
+    Cluster,
+    Neighbours,
+}
+
+impl Metric {
+    pub fn parse(s: &str) -> Option<Metric> {
+        match s {
+            "variance" => Some(Metric::Variance),
+            "entropy" => Some(Metric::Entropy),
+            "cluster" => Some(Metric::Cluster),
+            "neighbours" => Some(Metric::Neighbours),
+            _ => None,
+        }
+    }
+
+    // robots bunched into a picture have low spread and low entropy, but big clusters and lots of neighbours
+    pub fn minimise(&self) -> bool {
+        matches!(self, Metric::Variance | Metric::Entropy)
+    }
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Metric::Variance => write!(f, "variance"),
+            Metric::Entropy => write!(f, "entropy"),
+            Metric::Cluster => write!(f, "cluster"),
+            Metric::Neighbours => write!(f, "neighbours"),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct StepStats {
+    pub variance: f64,
+    pub entropy: f64,
+    pub largest_cluster: usize,
+    pub robots_with_neighbours: usize,
+}
+
+impl fmt::Display for StepStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "variance {:.2} entropy {:.4} largest cluster {} robots with neighbours {}",
+            self.variance, self.entropy, self.largest_cluster, self.robots_with_neighbours)
+    }
+}
+
+impl StepStats {
+    // counts is the number of robots in each cell, indexed by [y][x]
+    pub fn from_counts(counts: &[Vec<i32>]) -> StepStats {
+        StepStats {
+            variance: variance(counts),
+            entropy: entropy(counts),
+            largest_cluster: largest_cluster(counts),
+            robots_with_neighbours: robots_with_neighbours(counts),
+        }
+    }
+
+    pub fn value(&self, metric: Metric) -> f64 {
+        match metric {
+            Metric::Variance => self.variance,
+            Metric::Entropy => self.entropy,
+            Metric::Cluster => self.largest_cluster as f64,
+            Metric::Neighbours => self.robots_with_neighbours as f64,
+        }
+    }
+
+    pub fn is_better_than(&self, other: &StepStats, metric: Metric) -> bool {
+        if metric.minimise() {
+            self.value(metric) < other.value(metric)
+        } else {
+            self.value(metric) > other.value(metric)
+        }
+    }
+}
+
+// variance of the x positions plus variance of the y positions
+fn variance(counts: &[Vec<i32>]) -> f64 {
+    let mut n = 0.0;
+    let mut sum_x = 0.0;
+    let mut sum_y = 0.0;
+    let mut sum_xx = 0.0;
+    let mut sum_yy = 0.0;
+
+    for (y, row) in counts.iter().enumerate() {
+        for (x, count) in row.iter().enumerate() {
+            let weight = *count as f64;
+            n += weight;
+            sum_x += weight * x as f64;
+            sum_y += weight * y as f64;
+            sum_xx += weight * (x * x) as f64;
+            sum_yy += weight * (y * y) as f64;
+        }
+    }
+
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let mean_x = sum_x / n;
+    let mean_y = sum_y / n;
+
+    (sum_xx / n - mean_x * mean_x) + (sum_yy / n - mean_y * mean_y)
+}
+
+// Shannon entropy (in bits) of how the robots are spread across blocks of the arena
+fn entropy(counts: &[Vec<i32>]) -> f64 {
+    if counts.is_empty() {
+        return 0.0;
+    }
+
+    let block_rows = counts.len().div_ceil(ENTROPY_BLOCK_SIZE);
+    let block_cols = counts[0].len().div_ceil(ENTROPY_BLOCK_SIZE);
+    let mut blocks = vec![0i32; block_rows * block_cols];
+    let mut total = 0;
+
+    for (y, row) in counts.iter().enumerate() {
+        for (x, count) in row.iter().enumerate() {
+            blocks[(y / ENTROPY_BLOCK_SIZE) * block_cols + x / ENTROPY_BLOCK_SIZE] += count;
+            total += count;
+        }
+    }
+
+    let mut entropy = 0.0;
+    for block in blocks {
+        if block == 0 {
+            continue;
+        }
+        let p = block as f64 / total as f64;
+        entropy -= p * p.log2();
+    }
+
+    entropy
+}
+
+// number of robots in the biggest group of occupied cells that touch up, down, left or right
+fn largest_cluster(counts: &[Vec<i32>]) -> usize {
+    let height = counts.len();
+    if height == 0 {
+        return 0;
+    }
+    let width = counts[0].len();
+
+    let mut visited = vec![vec![false; width]; height];
+    let mut largest = 0;
+    let mut queue = VecDeque::new();
+
+    for start_y in 0..height {
+        for start_x in 0..width {
+            if visited[start_y][start_x] || counts[start_y][start_x] == 0 {
+                continue;
+            }
+
+            let mut cluster_size = 0;
+            visited[start_y][start_x] = true;
+            queue.push_back((start_x, start_y));
+
+            while let Some((x, y)) = queue.pop_front() {
+                cluster_size += counts[y][x] as usize;
+
+                let neighbours = [
+                    (x.wrapping_sub(1), y),
+                    (x + 1, y),
+                    (x, y.wrapping_sub(1)),
+                    (x, y + 1),
+                ];
+
+                for (next_x, next_y) in neighbours {
+                    if next_x >= width || next_y >= height {
+                        continue;
+                    }
+                    if visited[next_y][next_x] || counts[next_y][next_x] == 0 {
+                        continue;
+                    }
+                    visited[next_y][next_x] = true;
+                    queue.push_back((next_x, next_y));
+                }
+            }
+
+            largest = largest.max(cluster_size);
+        }
+    }
+
+    largest
+}
+
+// robots that have at least one other robot in one of the 8 surrounding cells
+fn robots_with_neighbours(counts: &[Vec<i32>]) -> usize {
+    let height = counts.len() as i32;
+    if height == 0 {
+        return 0;
+    }
+    let width = counts[0].len() as i32;
+
+    let mut total = 0;
+
+    for y in 0..height {
+        for x in 0..width {
+            let count = counts[y as usize][x as usize];
+            if count == 0 {
+                continue;
+            }
+
+            let mut has_neighbour = false;
+            for dy in -1..=1 {
+                for dx in -1..=1 {
+                    if dx == 0 && dy == 0 {
+                        continue;
+                    }
+                    let (nx, ny) = (x + dx, y + dy);
+                    if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                        continue;
+                    }
+                    if counts[ny as usize][nx as usize] > 0 {
+                        has_neighbour = true;
+                    }
+                }
+            }
+
+            if has_neighbour {
+                total += count as usize;
+            }
+        }
+    }
+
+    total
+}
@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Eq, Hash, PartialEq, Debug)]
+pub struct Coordinates {
+    pub row: usize,
+    pub col: usize,
+}
+
+#[derive(Copy, Clone, Eq, Hash, PartialEq, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    pub fn from_marker(marker: char) -> Option<Direction> {
+        match marker {
+            '^' => Some(Direction::Up),
+            'v' => Some(Direction::Down),
+            '<' => Some(Direction::Left),
+            '>' => Some(Direction::Right),
+            _ => None,
+        }
+    }
+
+    pub fn marker(&self) -> char {
+        match self {
+            Direction::Up => '^',
+            Direction::Down => 'v',
+            Direction::Left => '<',
+            Direction::Right => '>',
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Down => 1,
+            Direction::Left => 2,
+            Direction::Right => 3,
+        }
+    }
+
+    pub fn turn_right(&self) -> Direction {
+        match self {
+            Direction::Up => Direction::Right,
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+        }
+    }
+}
+
+// everything about the guard that changes as they walk, the map itself is never touched
+#[derive(Copy, Clone, Eq, Hash, PartialEq, Debug)]
+pub struct Guard {
+    pub pos: Coordinates,
+    pub dir: Direction,
+}
+
+impl Guard {
+    pub fn new(pos: Coordinates, dir: Direction) -> Guard {
+        Guard { pos, dir }
+    }
+
+    pub fn turn_right(&self) -> Guard {
+        Guard::new(self.pos, self.dir.turn_right())
+    }
+
+    // where the guard will be after one step, or None once they have left the map
+    pub fn step(&self, map: &PuzzleMap) -> Option<Guard> {
+        let mut new_pos = self.pos;
+
+        match self.dir {
+            Direction::Up => {
+                // try moving up
+                if new_pos.row == 0 {
+                    return None;
+                }
+                new_pos.row -= 1;
+            },
+            Direction::Down => {
+                // try moving down
+                new_pos.row += 1;
+            },
+            Direction::Right => {
+                // try moving right
+                new_pos.col += 1;
+            },
+            Direction::Left => {
+                // try moving left
+                if new_pos.col == 0 {
+                    return None;
+                }
+                new_pos.col -= 1;
+            },
+        }
+
+        if !map.contains(&new_pos) {
+            // moved out of the area
+            return None;
+        }
+
+        if map.is_obstacle(&new_pos) {
+            return Some(self.turn_right());
+        }
+
+        // no obstacle, continue forward
+        Some(Guard::new(new_pos, self.dir))
+    }
+}
+
+// the lab floor with only its obstacles, the guard's starting marker is taken out when parsing
+pub struct PuzzleMap {
+    rows: usize,
+    cols: usize,
+    obstacles: Vec<bool>,
+}
+
+impl PuzzleMap {
+    // returns the map along with the guard that was drawn on it
+    pub fn parse(lines: &[String]) -> (PuzzleMap, Guard) {
+        let rows = lines.len();
+        let cols = lines.first().map(|line| line.len()).unwrap_or(0);
+        let mut obstacles = vec![false; rows * cols];
+        let mut guard: Option<Guard> = None;
+
+        for (row, line) in lines.iter().enumerate() {
+            for (col, cell) in line.chars().enumerate() {
+                if cell == '#' {
+                    obstacles[row * cols + col] = true;
+                } else if let Some(dir) = Direction::from_marker(cell) {
+                    guard = Some(Guard::new(Coordinates { row, col }, dir));
+                }
+            }
+        }
+
+        (PuzzleMap { rows, cols, obstacles }, guard.expect("no guard on the map"))
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn contains(&self, pos: &Coordinates) -> bool {
+        pos.row < self.rows && pos.col < self.cols
+    }
+
+    pub fn is_obstacle(&self, pos: &Coordinates) -> bool {
+        self.obstacles[pos.row * self.cols + pos.col]
+    }
+}
+
+// every state the guard has been in, in order, and the step each one was first reached at
+pub struct VisitHistory {
+    route: Vec<Guard>,
+    first_seen: HashMap<Guard, usize>,
+}
+
+impl VisitHistory {
+    pub fn new() -> VisitHistory {
+        VisitHistory { route: vec![], first_seen: HashMap::new() }
+    }
+
+    // returns the step this state was first seen at if the guard has already been here facing the same way
+    pub fn visit(&mut self, guard: Guard, step: usize) -> Option<usize> {
+        if let Some(first_seen) = self.first_seen.get(&guard) {
+            return Some(*first_seen);
+        }
+        self.first_seen.insert(guard, step);
+        self.route.push(guard);
+        None
+    }
+
+    pub fn route(&self) -> &[Guard] {
+        &self.route
+    }
+}
@@ -2,65 +2,352 @@ use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
 use std::collections::HashSet;
-//use std::{thread, time};
 use std::env;
+use std::thread;
 
 
-#[derive(Copy, Clone, Eq, Hash, PartialEq)]
-struct Coordinates {
-    row: usize,
-    col: usize,
+mod guard;
+use guard::{Coordinates, Direction, Guard, PuzzleMap, VisitHistory};
+#[path = "../../utils/src/args.rs"] mod args;
+use args::flag_value;
+
+// the guard is in a loop as soon as it is back at a position it has already been at while facing the same way
+#[derive(Debug)]
+enum GuardOutcome {
+    Exit,
+    // entry is the first state the guard reaches that is part of the loop
+    Loop { cycle_length: usize, entry: Guard },
+}
+
+// marks a jump that takes the guard off the map
+const EXIT: usize = usize::MAX;
+
+/*
+for every cell and direction, the cell where the guard ends up standing in front of the next obstacle.
+cells are stored as row * cols + col so the guard can move obstacle to obstacle instead of cell by cell
+ */
+#[derive(Clone)]
+struct JumpTable {
+    rows: usize,
+    cols: usize,
+    stops: Vec<[usize; 4]>,
 }
 
-type PuzzleMap = Vec<Vec<char>>;
+impl JumpTable {
+    fn new(map: &PuzzleMap) -> JumpTable {
+        let rows = map.rows();
+        let cols = map.cols();
+        let mut table = JumpTable { rows, cols, stops: vec![[EXIT; 4]; rows * cols] };
+        let is_wall = |row: usize, col: usize| map.is_obstacle(&Coordinates { row, col });
+
+        for col in 0..cols {
+            let mut stop = EXIT;
+            for row in 0..rows {
+                if is_wall(row, col) {
+                    stop = table.index(row + 1, col);
+                } else {
+                    table.stops[row * cols + col][Direction::Up.index()] = stop;
+                }
+            }
+
+            let mut stop = EXIT;
+            for row in (0..rows).rev() {
+                if is_wall(row, col) {
+                    stop = table.index(row.wrapping_sub(1), col);
+                } else {
+                    table.stops[row * cols + col][Direction::Down.index()] = stop;
+                }
+            }
+        }
+
+        for row in 0..rows {
+            let mut stop = EXIT;
+            for col in 0..cols {
+                if is_wall(row, col) {
+                    stop = table.index(row, col + 1);
+                } else {
+                    table.stops[row * cols + col][Direction::Left.index()] = stop;
+                }
+            }
+
+            let mut stop = EXIT;
+            for col in (0..cols).rev() {
+                if is_wall(row, col) {
+                    stop = table.index(row, col.wrapping_sub(1));
+                } else {
+                    table.stops[row * cols + col][Direction::Right.index()] = stop;
+                }
+            }
+        }
+
+        table
+    }
+
+    fn index(&self, row: usize, col: usize) -> usize {
+        if row >= self.rows || col >= self.cols {
+            return EXIT;
+        }
+        row * self.cols + col
+    }
+
+    fn stop(&self, guard: &Guard) -> Option<Coordinates> {
+        let stop = self.stops[guard.pos.row * self.cols + guard.pos.col][guard.dir.index()];
+        if stop == EXIT {
+            return None;
+        }
+        Some(Coordinates { row: stop / self.cols, col: stop % self.cols })
+    }
+
+    /*
+    only the cells between the new obstacle and the next '#' on its row and column can bump into it,
+    so only those entries change. returns what was overwritten so the obstacle can be taken away again
+     */
+    fn insert_obstacle(&mut self, map: &PuzzleMap, obstacle: &Coordinates) -> Vec<(usize, usize, usize)> {
+        let mut undo = vec![];
+        let (row, col) = (obstacle.row, obstacle.col);
+        let is_wall = |row: usize, col: usize| map.is_obstacle(&Coordinates { row, col });
+
+        let below = self.index(row + 1, col);
+        for r in (row + 1..self.rows).take_while(|r| !is_wall(*r, col)) {
+            self.set_stop(r * self.cols + col, Direction::Up, below, &mut undo);
+        }
+
+        let above = self.index(row.wrapping_sub(1), col);
+        for r in (0..row).rev().take_while(|r| !is_wall(*r, col)) {
+            self.set_stop(r * self.cols + col, Direction::Down, above, &mut undo);
+        }
+
+        let right = self.index(row, col + 1);
+        for c in (col + 1..self.cols).take_while(|c| !is_wall(row, *c)) {
+            self.set_stop(row * self.cols + c, Direction::Left, right, &mut undo);
+        }
+
+        let left = self.index(row, col.wrapping_sub(1));
+        for c in (0..col).rev().take_while(|c| !is_wall(row, *c)) {
+            self.set_stop(row * self.cols + c, Direction::Right, left, &mut undo);
+        }
+
+        undo
+    }
+
+    fn set_stop(&mut self, cell: usize, direction: Direction, stop: usize, undo: &mut Vec<(usize, usize, usize)>) {
+        undo.push((cell, direction.index(), self.stops[cell][direction.index()]));
+        self.stops[cell][direction.index()] = stop;
+    }
+
+    fn remove_obstacle(&mut self, undo: Vec<(usize, usize, usize)>) {
+        for (cell, direction, stop) in undo.into_iter().rev() {
+            self.stops[cell][direction] = stop;
+        }
+    }
+}
 
 type PuzzleHashSet = HashSet<Coordinates>;
 
+const USAGE: &str = "usage: day6 [file] [--jobs <threads>] [--report]";
+
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
 
-    let mut map: PuzzleMap = vec![];
+    let mut file_name: &str = "input";
+    let mut jobs: usize = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+    let mut report = false;
+
+    let cmd_args: Vec<String> = env::args().collect();
+
+    let mut i = 1;
+    while i < cmd_args.len() {
+        match cmd_args[i].as_str() {
+            "--jobs" => {
+                let Some(threads) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                jobs = threads.parse::<usize>().expect("--jobs needs a number").max(1);
+            },
+            "--report" => {
+                report = true;
+            },
+            arg => file_name = arg,
+        }
+        i += 1;
+    }
 
-    if let Ok(lines) = read_lines("input") {
+    let mut lines: Vec<String> = vec![];
+
+    if let Ok(file_lines) = read_lines(file_name) {
         // Consumes the iterator, returns an (Optional) String
-        for line in lines.flatten() {
-            map.push(line.chars().collect());
+        lines = file_lines.map_while(Result::ok).collect();
+    }
+
+    let (map, start_guard) = PuzzleMap::parse(&lines);
+
+    // walk the route once without any extra obstacle, remembering the state before each step
+    let history = walk_route(&map, start_guard);
+    let route = history.route();
+    let guard_step_set: PuzzleHashSet = route.iter().map(|guard| guard.pos).collect();
+
+    /*
+    an obstacle can only change anything if it is somewhere the guard would walk. the walk up to the first
+    time the guard reaches that cell is not affected by it, so each trial starts from the state just before
+     */
+    let mut candidates: Vec<(Coordinates, Guard)> = vec![];
+    let mut candidate_set = PuzzleHashSet::new();
+    candidate_set.insert(start_guard.pos);
+    for window in route.windows(2) {
+        if candidate_set.insert(window[1].pos) {
+            candidates.push((window[1].pos, window[0]));
         }
     }
 
-    let mut guard_position = get_guard_position(&map);
+    let jump_table = JumpTable::new(&map);
+    let results = try_obstacles_in_parallel(&map, &jump_table, &candidates, jobs);
+
     let mut obstacle_set = PuzzleHashSet::new();
-    let mut guard_step_set = PuzzleHashSet::new();
-    let starting_position = guard_position.clone();
-
-    // try placing an obstacle at each position
-    for row in 0..map.len() {
-        for col in 0..map[0].len() {
-            let obstacle_position = Coordinates {row: row, col: col};
-            if map[row][col] == '#' ||  obstacle_position == starting_position {
-                // can't place an obstacle here
-                continue;
+    let mut loop_obstacles: Vec<(Coordinates, usize)> = vec![];
+    let mut longest_loop: Option<(usize, Coordinates, Guard)> = None;
+
+    for (obstacle_position, outcome) in results {
+        if let GuardOutcome::Loop { cycle_length, entry } = outcome {
+            obstacle_set.insert(obstacle_position);
+            loop_obstacles.push((obstacle_position, cycle_length));
+            if longest_loop.is_none_or(|(longest_cycle, _, _)| cycle_length > longest_cycle) {
+                longest_loop = Some((cycle_length, obstacle_position, entry));
             }
-
-            try_trapping_guard(&mut map.clone(), &mut guard_position.clone(), &mut obstacle_set, &obstacle_position);
         }
     }
 
-    while guard_is_in_puzzle_map(&map, &guard_position) {
-        guard_step_set.insert(guard_position);
-        guard_step(&mut map, &mut guard_position, 'X');
+    if report {
+        print_report(&map, route, &loop_obstacles);
     }
 
     println!("guard positions {}", guard_step_set.len());
     println!("obstacles placed {}", obstacle_set.len());
+
+    if let Some((cycle_length, obstacle_position, entry)) = longest_loop {
+        // the trial started part way along the route, walk it again from the very start to see where it joins the loop
+        let mut jump_table = jump_table.clone();
+        jump_table.insert_obstacle(&map, &obstacle_position);
+        let entry = match try_trapping_guard(&jump_table, start_guard) {
+            GuardOutcome::Loop { entry, .. } => entry,
+            GuardOutcome::Exit => entry,
+        };
+        println!("longest loop {} steps with obstacle at {},{}, entered at {},{} facing {:?}",
+            cycle_length, obstacle_position.row, obstacle_position.col, entry.pos.row, entry.pos.col, entry.dir);
+    }
     
 }
 
-fn print_map(map: &PuzzleMap, guard_position: &Coordinates) {
-    let mut start_row = guard_position.row;
-    let mut start_col = guard_position.col;
-    let mut end_row = guard_position.row;
-    let mut end_col = guard_position.col;
+fn print_report(map: &PuzzleMap, route: &[Guard], loop_obstacles: &[(Coordinates, usize)]) {
+    println!("route:");
+    for (step, guard) in route.iter().enumerate() {
+        if step > 0 && route[step - 1].pos == guard.pos {
+            // same place as the step before, so the guard turned here
+            println!("{} {},{} turn {}", step, guard.pos.row, guard.pos.col, guard.dir.marker());
+        } else {
+            println!("{} {},{} {}", step, guard.pos.row, guard.pos.col, guard.dir.marker());
+        }
+    }
+
+    let mut sorted_obstacles = loop_obstacles.to_vec();
+    sorted_obstacles.sort_by_key(|(pos, _)| (pos.row, pos.col));
+
+    println!("obstacles causing a loop:");
+    for (pos, cycle_length) in &sorted_obstacles {
+        println!("{},{} loop length {}", pos.row, pos.col, cycle_length);
+    }
+
+    for line in render_report_map(map, route, &sorted_obstacles) {
+        println!("{}", line);
+    }
+}
+
+/*
+draws the route over the map: | and - where the guard walked up/down or left/right, + where they turned
+or crossed their own path, O where an obstacle would trap them and the guard's marker where they started
+ */
+fn render_report_map(map: &PuzzleMap, route: &[Guard], loop_obstacles: &[(Coordinates, usize)]) -> Vec<String> {
+    let mut grid: Vec<Vec<char>> = vec![vec!['.'; map.cols()]; map.rows()];
+
+    for (row, grid_row) in grid.iter_mut().enumerate() {
+        for (col, cell) in grid_row.iter_mut().enumerate() {
+            if map.is_obstacle(&Coordinates { row, col }) {
+                *cell = '#';
+            }
+        }
+    }
+
+    for guard in route {
+        let cell = &mut grid[guard.pos.row][guard.pos.col];
+        let path = match guard.dir {
+            Direction::Up | Direction::Down => '|',
+            Direction::Left | Direction::Right => '-',
+        };
+
+        *cell = match *cell {
+            '.' => path,
+            existing if existing == path => path,
+            _ => '+',
+        };
+    }
+
+    for (pos, _) in loop_obstacles {
+        grid[pos.row][pos.col] = 'O';
+    }
+
+    if let Some(start) = route.first() {
+        grid[start.pos.row][start.pos.col] = start.dir.marker();
+    }
+
+    grid.into_iter().map(|row| row.into_iter().collect()).collect()
+}
+
+/*
+split the candidate obstacles between threads, the map is only ever read so they can all share it.
+each thread gets its own copy of the jump table to put its obstacles into
+ */
+fn try_obstacles_in_parallel(map: &PuzzleMap, jump_table: &JumpTable, candidates: &[(Coordinates, Guard)], jobs: usize) -> Vec<(Coordinates, GuardOutcome)> {
+    let chunk_size = candidates.len().div_ceil(jobs).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = candidates.chunks(chunk_size).map(|chunk| {
+            let mut jump_table = jump_table.clone();
+            scope.spawn(move || {
+                chunk.iter()
+                    .map(|(obstacle_position, start)| {
+                        let undo = jump_table.insert_obstacle(map, obstacle_position);
+                        let outcome = try_trapping_guard(&jump_table, *start);
+                        jump_table.remove_obstacle(undo);
+                        (*obstacle_position, outcome)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        }).collect();
+
+        handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+    })
+}
+
+fn walk_route(map: &PuzzleMap, start: Guard) -> VisitHistory {
+    let mut history = VisitHistory::new();
+    let mut guard = Some(start);
+    let mut steps: usize = 0;
+
+    while let Some(current) = guard {
+        if history.visit(current, steps).is_some() {
+            println!("guard is stuck in a loop without any extra obstacle");
+            break;
+        }
+        guard = current.step(map);
+        steps += 1;
+    }
+
+    history
+}
+
+#[allow(dead_code)]
+fn print_map(map: &PuzzleMap, guard: &Guard) {
+    let mut start_row = guard.pos.row;
+    let mut start_col = guard.pos.col;
+    let mut end_row = guard.pos.row;
+    let mut end_col = guard.pos.col;
 
     let mut row_offset = 20;
     let mut col_offset = 20;
@@ -79,14 +366,14 @@ fn print_map(map: &PuzzleMap, guard_position: &Coordinates) {
         start_col = 0;
     }
 
-    if end_row + row_offset >= map.len() {
-        end_row = map.len();
+    if end_row + row_offset >= map.rows() {
+        end_row = map.rows();
     } else {
         end_row += row_offset;
     }
 
-    if end_col + col_offset >= map[0].len() {
-        end_col = map[0].len();
+    if end_col + col_offset >= map.cols() {
+        end_col = map.cols();
     } else {
         end_col += col_offset;
     }
@@ -96,150 +383,62 @@ fn print_map(map: &PuzzleMap, guard_position: &Coordinates) {
 
     for row in start_row..end_row {
         for col in start_col..end_col {
-            print!("{} ", map[row][col]);
+            let pos = Coordinates { row, col };
+            if pos == guard.pos {
+                print!("{} ", guard.dir.marker());
+            } else if map.is_obstacle(&pos) {
+                print!("# ");
+            } else {
+                print!(". ");
+            }
         }
         println!("");
     }
 }
 
-fn try_trapping_guard(map: &mut PuzzleMap, guard_position: &mut Coordinates, obstacle_set: &mut PuzzleHashSet, obstacle_position: &Coordinates) -> bool {
-    // set an obstacle in the guard's path and see if they get in a loop
-
-    //println!("{},{} try trapping at", guard_position.row, guard_position.col);
-
-    let mut puzzle_set = PuzzleHashSet::new();
-
-    map[obstacle_position.row][obstacle_position.col] = 'O';
-
-    let mut count_down = 0;
-    let mut last_marker_count = 0;
-    let mut current_marker_count: usize;
-    
-    while guard_is_in_puzzle_map(&map, &guard_position) {
-
-        //print_map(&map, &guard_position);
-        //thread::sleep(time::Duration::from_millis(10));
+// the obstacle has already been put into the jump table
+fn try_trapping_guard(jump_table: &JumpTable, start: Guard) -> GuardOutcome {
+    let mut history = VisitHistory::new();
+    let mut steps: usize = 0;
+    let mut guard = start;
 
-        /*
-        if is_guard_at_start_of_loop(&map, &guard_position) {
-            return true;
+    loop {
+        if let Some(first_seen) = history.visit(guard, steps) {
+            // been here before facing the same way, so the guard will repeat this forever
+            return GuardOutcome::Loop { cycle_length: steps - first_seen, entry: loop_entry(history.route(), guard) };
         }
-        */
-        puzzle_set.insert(*guard_position);
 
-        guard_step(map, guard_position, '/');
+        let Some(stop) = jump_table.stop(&guard) else {
+            return GuardOutcome::Exit;
+        };
 
-        // count the number of markers at each step and see if it is increasing
-        current_marker_count = puzzle_set.len();
-        if current_marker_count == last_marker_count {
-            if count_down == 0 {
-                /*
-                * start counting down from the current count and give the guard that many steps to see if it is stuck in a loop
-                */
-                count_down = 4 + current_marker_count;
-            } else {
-                count_down -= 1;
-                
-                //println!("{} {} {}", current_marker_count, last_marker_count, count_down);
-                if count_down == 0 {
-                    // this is likely a loop
-                    obstacle_set.insert(*obstacle_position);
-                    //println!("=========================================================================");
-                    //print_map(&map, &guard_position);
-                    return true;
-                }
-            }
-        } else {
-            count_down = 0;
-            last_marker_count = current_marker_count;
-        }
+        // walking up to the obstacle, then one more step to turn
+        steps += guard.pos.row.abs_diff(stop.row) + guard.pos.col.abs_diff(stop.col) + 1;
+        guard = Guard::new(stop, guard.dir).turn_right();
     }
-    return false;
-}
-
-fn guard_is_in_puzzle_map(map: &PuzzleMap, guard_position: &Coordinates) -> bool {
-    return guard_position.row < map.len() && guard_position.col < map[0].len();
 }
 
-fn guard_step(map: &mut PuzzleMap, guard_position: &mut Coordinates, guard_position_marker: char) {
-
-    //println!("{},{}", guard_position.row, guard_position.col);
-
-    let mut new_guard_position = Coordinates {row: guard_position.row, col: guard_position.col};
-    
-    match map[guard_position.row][guard_position.col] {
-        '^' => {
-            // try moving up
-            if new_guard_position.row == 0 {
-                new_guard_position.row = usize::MAX;
-            }
-            new_guard_position.row -= 1;
-        },
-        'v' => {
-            // try moving down
-            new_guard_position.row += 1;
-        },
-        '>' => {
-            // try moving right
-            new_guard_position.col += 1;
-        },
-        '<' => {
-            // try moving left
-            if new_guard_position.col == 0 {
-                new_guard_position.col = usize::MAX;
-            }
-            new_guard_position.col -= 1;
-        },
-        _ => {
-            print_map(&map, &guard_position);
-            panic!();
-        },
-    }
-
-    if guard_is_in_puzzle_map(&map, &new_guard_position) {
-        // check for an obstacle
-        if map[new_guard_position.row][new_guard_position.col] == '#' || map[new_guard_position.row][new_guard_position.col] == 'O' {
-            // turn right
-            match map[guard_position.row][guard_position.col] {
-                '^' => {
-                    map[guard_position.row][guard_position.col] = '>';
-                },
-                'v' => {
-                    map[guard_position.row][guard_position.col] = '<';
-                },
-                '>' => {
-                    map[guard_position.row][guard_position.col] = 'v';
-                },
-                '<' => {
-                    map[guard_position.row][guard_position.col] = '^';
-                },
-                _ => panic!(),
-            }
-        } else {
-            // no obstacle, continue forward
-            map[new_guard_position.row][new_guard_position.col] = map[guard_position.row][guard_position.col];
-            map[guard_position.row][guard_position.col] = guard_position_marker; // mark where the guard has been
-            guard_position.row = new_guard_position.row;
-            guard_position.col = new_guard_position.col;
-        }
-    } else {
-        // moved out of the area
-        map[guard_position.row][guard_position.col] = guard_position_marker;
-        guard_position.row = usize::MAX;
-        guard_position.col = usize::MAX;
+/*
+* the first state on the guard's route that is part of the loop. only the states after each jump are in the route,
+* so the repeated one is just the first turn inside the loop. the jump into it from before the loop and the jump that
+* closes the loop both head the same way to the same stop, so the route joins the loop at whichever of the two
+* starts closer to that stop
+*/
+fn loop_entry(route: &[Guard], repeated: Guard) -> Guard {
+    let index = route.iter().position(|guard| *guard == repeated).unwrap();
+    if index == 0 {
+        // the guard started on the loop
+        return repeated;
     }
-}
 
-fn get_guard_position(map: &PuzzleMap) -> Coordinates {
-    for row in 0..map.len() {
-        for col in 0..map[row].len() {
-            match map[row][col] {
-                '^' | 'v' | '>' | '<' => return Coordinates { row: row, col: col},
-                _ => continue,
-            }
-        }
+    let before_loop = route[index - 1];
+    let closing = *route.last().unwrap();
+    let distance = |guard: &Guard| guard.pos.row.abs_diff(repeated.pos.row) + guard.pos.col.abs_diff(repeated.pos.col);
+    if distance(&before_loop) < distance(&closing) {
+        before_loop
+    } else {
+        closing
     }
-    panic!();
 }
 
 // The output is wrapped in a Result to allow matching on errors.
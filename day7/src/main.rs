@@ -1,71 +1,220 @@
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::env;
 
-mod permutations;
-use permutations::ToPermutationsWithReplacement;
+#[path = "../../utils/src/combinatorics.rs"]
+#[allow(dead_code)]
+mod combinatorics;
+use combinatorics::ToPermutationsWithReplacement;
+mod operators;
+use operators::{parse_operators, Inverse, Operator};
+#[path = "../../utils/src/args.rs"] mod args;
+use args::flag_value;
+
+const USAGE: &str = "usage: day7 [file] [--brute-force] [--explain] [--operators <comma separated symbols>]";
 
 fn main() {
-    if let Ok(lines) = read_lines("input") {
+    let mut file_name: &str = "input";
+    let mut brute_force = false;
+    let mut explain = false;
+    let mut operator_list: &str = "+,*,|";
+
+    let cmd_args: Vec<String> = env::args().collect();
+
+    let mut i = 1;
+    while i < cmd_args.len() {
+        match cmd_args[i].as_str() {
+            "--brute-force" => brute_force = true,
+            "--explain" => explain = true,
+            "--operators" => {
+                let Some(operators) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                operator_list = operators;
+            },
+            arg => file_name = arg,
+        }
+        i += 1;
+    }
+
+    let possible_operators = parse_operators(operator_list);
+
+    let symbols: Vec<String> = possible_operators.iter().map(|operator| operator.symbol()).collect();
+    println!("operators {}", symbols.join(" "));
+
+    if let Ok(lines) = read_lines(file_name) {
         // Consumes the iterator, returns an (Optional) String
 
-        let mut sum = 0;
-        for line in lines.flatten() {
-            let (test_value, inputs) = parse_line(line);
+        // the sum of a lot of big test values can go past an i64 even when each one fits
+        let mut sum: i128 = 0;
+        let mut unsolvable: Vec<String> = vec![];
+        for line in lines.map_while(Result::ok) {
+            let Some((test_value, inputs)) = parse_line(&line) else {
+                println!("skipping unreadable line: {}", line);
+                continue;
+            };
 
-            if has_valid_equation(&test_value, &inputs) {
-                sum += test_value;
+            let solved = if explain {
+                explain_equation(test_value, &inputs, &possible_operators)
+            } else if brute_force {
+                has_valid_equation(&test_value, &inputs, &possible_operators)
+            } else {
+                solve_equation(test_value, &inputs, &possible_operators).is_some()
+            };
+
+            if solved {
+                sum += test_value as i128;
+            } else {
+                unsolvable.push(line);
             }
 
             //println!("{} : {:?}", test_value, inputs);
         }
 
+        if explain {
+            println!("unsolvable lines {}", unsolvable.len());
+            for line in &unsolvable {
+                println!("{}", line);
+            }
+        }
+
         println!("sum {}", sum);
 
     }
 }
 
-fn has_valid_equation(test_value: &i64, inputs: &Vec<i64>) -> bool {
-    assert!(inputs.len() >= 2, "inputs length < 2");
+// prints the first solution found as an equation along with how many operator sequences work
+fn explain_equation(test_value: i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>]) -> bool {
+    let Some(operators) = solve_equation(test_value, inputs, possible_operators) else {
+        return false;
+    };
+
+    let mut expression = inputs[0].to_string();
+    for (operator, input) in operators.iter().zip(&inputs[1..]) {
+        expression.push_str(&format!(" {} {}", possible_operators[*operator].symbol(), input));
+    }
+
+    let ways = count_solutions(test_value, inputs, possible_operators);
+    println!("{} = {} ({} {})", test_value, expression, ways, if ways == 1 { "way" } else { "ways" });
+
+    true
+}
+
+// returns the index of each operator in the first solution found, in left to right order
+fn solve_equation(test_value: i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>]) -> Option<Vec<usize>> {
+    let mut solution = None;
+    search_equation(test_value, inputs, possible_operators, &mut |operators| {
+        solution = Some(operators.to_vec());
+        true
+    });
+    solution
+}
+
+// how many operator sequences work, the same search but it never stops early
+fn count_solutions(test_value: i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>]) -> u64 {
+    let mut ways = 0;
+    search_equation(test_value, inputs, possible_operators, &mut |_| {
+        ways += 1;
+        false
+    });
+    ways
+}
+
+/*
+work backwards from the test value, undoing the last operator each time (subtract for +, divide for * only when
+it divides evenly, strip the last number's digits off the end for | and so on). any branch that can't be undone
+is dropped straight away, so most of the operator combinations never get looked at. operators without an inverse
+are tried forwards over everything before them instead.
+visit is given the index of each operator, in left to right order, for every solution found and returns true to
+stop searching. returns true if the search was stopped.
+a lone input needs no operators, it just has to be the test value
+ */
+fn search_equation(test_value: i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>], visit: &mut dyn FnMut(&[usize]) -> bool) -> bool {
+    // if nothing can go negative then neither can any partial result, so negative targets are dead ends
+    let non_negative = inputs.iter().all(|input| *input >= 0)
+        && possible_operators.iter().all(|operator| operator.keeps_non_negative());
+
+    let mut operators = vec![0; inputs.len() - 1];
+    search_backwards(test_value, inputs, possible_operators, non_negative, &mut operators, visit)
+}
+
+// inputs is always the start of the full list, so the operator before the last input goes in operators[inputs.len() - 2]
+fn search_backwards(target: i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>], non_negative: bool,
+                    operators: &mut [usize], visit: &mut dyn FnMut(&[usize]) -> bool) -> bool {
+    let (last, rest) = inputs.split_last().unwrap();
+
+    if rest.is_empty() {
+        return target == *last && visit(operators);
+    }
+
+    if non_negative && target < 0 {
+        return false;
+    }
+
+    for (index, operator) in possible_operators.iter().enumerate() {
+        operators[rest.len() - 1] = index;
+
+        let stopped = match operator.inverse(target, *last) {
+            Some(Inverse::Impossible) => false,
+            Some(Inverse::Left(remaining_target)) => search_backwards(remaining_target, rest, possible_operators, non_negative, operators, visit),
+            // whatever comes before works, as long as it can be worked out at all
+            Some(Inverse::Any) => search_forwards(rest[0], &rest[1..], possible_operators, &|_| true, operators, 0, visit),
+            None => search_forwards(rest[0], &rest[1..], possible_operators, &|value| operator.apply(value, *last) == Some(target), operators, 0, visit),
+        };
+
+        if stopped {
+            return true;
+        }
+    }
+
+    false
+}
+
+// try every operator left to right from the running total until accept is happy with the final value.
+// the operator for the next input goes in operators[position]
+fn search_forwards(total: i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>], accept: &dyn Fn(i64) -> bool,
+                   operators: &mut [usize], position: usize, visit: &mut dyn FnMut(&[usize]) -> bool) -> bool {
+    let Some((next, rest)) = inputs.split_first() else {
+        return accept(total) && visit(operators);
+    };
+
+    for (index, operator) in possible_operators.iter().enumerate() {
+        let Some(new_total) = operator.apply(total, *next) else {
+            continue;
+        };
+
+        operators[position] = index;
+        if search_forwards(new_total, rest, possible_operators, accept, operators, position + 1, visit) {
+            return true;
+        }
+    }
+
+    false
+}
+
+fn has_valid_equation(test_value: &i64, inputs: &[i64], possible_operators: &[Box<dyn Operator>]) -> bool {
+    if inputs.len() == 1 {
+        // no operators to choose, the one input has to be the test value already
+        return inputs[0] == *test_value;
+    }
 
-    let possible_operators: Vec<char> = vec!['+', '*', '|'];
     let num_operators = inputs.len() - 1;
 
-    let operator_permutations = (0..possible_operators.len()).permutations_with_replacement(num_operators);
+    let mut operator_permutations = (0..possible_operators.len()).permutations_with_replacement(num_operators);
 
-    for operator_permutation in operator_permutations {
-        let mut total: i64 = 0;
+    // next_ref reuses one buffer for every permutation instead of allocating a Vec each time
+    'permutations: while let Some(operator_permutation) = operator_permutations.next_ref() {
+        let mut total: i64 = inputs[0];
 
         //print!("test: {} ; {} ", test_value, inputs[0]);
 
         for i in 0..operator_permutation.len() {
 
-            //print!("{} {} ", possible_operators[operator_permutation[i]], inputs[i+1]);
-
-            match possible_operators[operator_permutation[i]] {
-                '+' => {
-
-                    if i == 0 {
-                        total = inputs[0] + inputs[1];
-                    } else {
-                        total += inputs[i+1];
-                    }
-                },
-                '*' => {
-                    if i == 0 {
-                        total = inputs[0] * inputs[1];
-                    } else {
-                        total *= inputs[i+1];
-                    }
-                },
-                '|' => {
-                    if i == 0 {
-                        total = concat_numbers(inputs[0], inputs[1]);
-                    } else {
-                        total = concat_numbers(total, inputs[i+1]);
-                    }
-                },
-                _ => panic!(),
+            //print!("{} {} ", possible_operators[operator_permutation[i]].symbol(), inputs[i+1]);
+
+            match possible_operators[operator_permutation[i]].apply(total, inputs[i+1]) {
+                Some(new_total) => total = new_total,
+                // not defined for these numbers, so this can't be the answer
+                None => continue 'permutations,
             }
         }
 
@@ -81,47 +230,33 @@ fn has_valid_equation(test_value: &i64, inputs: &Vec<i64>) -> bool {
         }
     }
 
-    return false;
+    false
 }
 
-fn concat_numbers(num1: i64, num2: i64) -> i64 {
-    let mut concat_result: String = num1.to_string();
-    concat_result.push_str(&num2.to_string());
-
-    match concat_result.parse::<i64>() {
-        Ok(concat_number) => {
-            return concat_number;
-        },
-        Err(_) => {
-            panic!();
-        },                 
-    }
-}
-
-fn parse_line(line: String) -> (i64, Vec<i64>) {
+// None if there are no inputs or any of the numbers can't be read, including ones too big for an i64
+fn parse_line(line: &str) -> Option<(i64, Vec<i64>)> {
     //println!("{}", line);
     let numbers: Vec<&str> = line.split(":").collect();
-    match numbers[0].to_string().parse::<i64>() {
-        Ok(test_value) => {
-            let inputs_str: Vec<&str> = numbers[1].split(" ").collect();
-            let mut inputs: Vec<i64> = vec![];
-            for input_str in inputs_str {
-                if input_str.len() == 0 {
-                    // skip empty strings
-                    continue;
-                }
-                match input_str.to_string().parse::<i64>() {
-                    Ok(input) => {
-                        inputs.push(input);
-                    },
-                    Err(e) => println!("{}", e),
-                }
-            }
-            return (test_value, inputs);
-        },
-        Err(e) => println!("{}", e),
+    if numbers.len() != 2 {
+        return None;
     }
-    panic!();
+
+    let test_value = numbers[0].trim().parse::<i64>().ok()?;
+
+    let mut inputs: Vec<i64> = vec![];
+    for input_str in numbers[1].split(" ") {
+        if input_str.is_empty() {
+            // skip empty strings
+            continue;
+        }
+        inputs.push(input_str.parse::<i64>().ok()?);
+    }
+
+    if inputs.is_empty() {
+        return None;
+    }
+
+    Some((test_value, inputs))
 }
 
 // The output is wrapped in a Result to allow matching on errors.
@@ -0,0 +1,253 @@
+// what an operator's inverse says about the left hand side, given the result and the right hand side
+pub enum Inverse {
+    // no left hand value gives this result
+    Impossible,
+    // exactly this left hand value gives this result
+    Left(i64),
+    // every left hand value gives this result, like anything * 0 == 0
+    Any,
+}
+
+pub trait Operator: Sync {
+    fn symbol(&self) -> String;
+
+    /*
+    None when the operator isn't defined for these numbers, like dividing by zero, or when the answer doesn't fit
+    in an i64. an equation that overflows part way through can't be the solution, so it is just skipped
+     */
+    fn apply(&self, left: i64, right: i64) -> Option<i64>;
+
+    /*
+    undo the operator so the solver can work backwards from the result. operators that can't be undone
+    (or where too many left hand values would work) return None and the solver tries them forwards instead
+     */
+    fn inverse(&self, _result: i64, _right: i64) -> Option<Inverse> {
+        None
+    }
+
+    // false if the operator can turn non-negative numbers into a negative one
+    fn keeps_non_negative(&self) -> bool {
+        true
+    }
+}
+
+pub struct Add;
+
+impl Operator for Add {
+    fn symbol(&self) -> String {
+        "+".to_string()
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_add(right)
+    }
+
+    fn inverse(&self, result: i64, right: i64) -> Option<Inverse> {
+        Some(left_or_impossible(result.checked_sub(right)))
+    }
+}
+
+pub struct Subtract;
+
+impl Operator for Subtract {
+    fn symbol(&self) -> String {
+        "-".to_string()
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_sub(right)
+    }
+
+    fn inverse(&self, result: i64, right: i64) -> Option<Inverse> {
+        Some(left_or_impossible(result.checked_add(right)))
+    }
+
+    fn keeps_non_negative(&self) -> bool {
+        false
+    }
+}
+
+pub struct Multiply;
+
+impl Operator for Multiply {
+    fn symbol(&self) -> String {
+        "*".to_string()
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_mul(right)
+    }
+
+    fn inverse(&self, result: i64, right: i64) -> Option<Inverse> {
+        if right == 0 {
+            return Some(if result == 0 { Inverse::Any } else { Inverse::Impossible });
+        }
+        if result.checked_rem(right) != Some(0) {
+            return Some(Inverse::Impossible);
+        }
+        Some(left_or_impossible(result.checked_div(right)))
+    }
+}
+
+// integer division rounding towards zero, a whole range of left hand values give the same result so it has no inverse
+pub struct Divide;
+
+impl Operator for Divide {
+    fn symbol(&self) -> String {
+        "/".to_string()
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        left.checked_div(right)
+    }
+}
+
+pub struct Xor;
+
+impl Operator for Xor {
+    fn symbol(&self) -> String {
+        "^".to_string()
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        Some(left ^ right)
+    }
+
+    fn inverse(&self, result: i64, right: i64) -> Option<Inverse> {
+        Some(Inverse::Left(result ^ right))
+    }
+}
+
+pub struct Power;
+
+impl Operator for Power {
+    fn symbol(&self) -> String {
+        "**".to_string()
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        let exponent = u32::try_from(right).ok()?;
+        left.checked_pow(exponent)
+    }
+
+    fn inverse(&self, result: i64, right: i64) -> Option<Inverse> {
+        if right == 1 {
+            return Some(Inverse::Left(result));
+        }
+        if right < 0 || result < 0 {
+            // negative roots are left to the forward search
+            return None;
+        }
+        if right == 0 {
+            return Some(if result == 1 { Inverse::Any } else { Inverse::Impossible });
+        }
+
+        // binary search for the largest root whose power doesn't go past the result, a power that overflows is too big
+        let (mut low, mut high) = (0, result);
+        while low < high {
+            let middle = high - (high - low) / 2;
+            match self.apply(middle, right) {
+                Some(power) if power <= result => low = middle,
+                _ => high = middle - 1,
+            }
+        }
+
+        if self.apply(low, right) != Some(result) {
+            return Some(Inverse::Impossible);
+        }
+        if right % 2 == 0 && low != 0 {
+            // -root works too for even powers, so leave both to the forward search
+            return None;
+        }
+        Some(Inverse::Left(low))
+    }
+}
+
+// writes the digits of the right hand side on the end of the left hand side, in the given base.
+// only defined when both sides are non-negative
+pub struct Concatenate {
+    pub base: i64,
+}
+
+impl Concatenate {
+    // base to the power of the number of digits in n, what the left hand side gets shifted up by
+    fn shift(&self, n: i64) -> Option<i64> {
+        let mut shift = self.base;
+        while shift <= n {
+            shift = shift.checked_mul(self.base)?;
+        }
+        Some(shift)
+    }
+}
+
+impl Operator for Concatenate {
+    fn symbol(&self) -> String {
+        if self.base == 10 {
+            "|".to_string()
+        } else {
+            format!("|{}", self.base)
+        }
+    }
+
+    fn apply(&self, left: i64, right: i64) -> Option<i64> {
+        if left < 0 || right < 0 {
+            return None;
+        }
+        left.checked_mul(self.shift(right)?)?.checked_add(right)
+    }
+
+    fn inverse(&self, result: i64, right: i64) -> Option<Inverse> {
+        if right < 0 {
+            return None;
+        }
+
+        // strip the right hand side's digits back off the end, if the result ends with them
+        let Some(shift) = self.shift(right) else {
+            // the shift alone doesn't fit in an i64, so apply can't give any result
+            return Some(Inverse::Impossible);
+        };
+        if result >= 0 && result % shift == right {
+            Some(Inverse::Left(result / shift))
+        } else {
+            Some(Inverse::Impossible)
+        }
+    }
+}
+
+fn left_or_impossible(left: Option<i64>) -> Inverse {
+    match left {
+        Some(left) => Inverse::Left(left),
+        // the left hand side would need to be outside the range of an i64
+        None => Inverse::Impossible,
+    }
+}
+
+/*
+operators are written as a comma separated list of symbols:
++ - * / ^ (xor) ** (power) | (concatenate) and |N to concatenate in base N
+ */
+pub fn parse_operators(list: &str) -> Vec<Box<dyn Operator>> {
+    let mut operators: Vec<Box<dyn Operator>> = vec![];
+
+    for symbol in list.split(",") {
+        let operator: Box<dyn Operator> = match symbol.trim() {
+            "+" => Box::new(Add),
+            "-" => Box::new(Subtract),
+            "*" => Box::new(Multiply),
+            "/" => Box::new(Divide),
+            "^" => Box::new(Xor),
+            "**" => Box::new(Power),
+            "|" => Box::new(Concatenate { base: 10 }),
+            other => {
+                let base = other.strip_prefix("|")
+                    .and_then(|base| base.parse::<i64>().ok())
+                    .filter(|base| *base >= 2)
+                    .unwrap_or_else(|| panic!("unknown operator {}", other));
+                Box::new(Concatenate { base })
+            },
+        };
+        operators.push(operator);
+    }
+
+    operators
+}
@@ -1,79 +0,0 @@
-/*
-* Credit Locke:
-  https://stackoverflow.com/questions/71420176/permutations-with-replacement-in-rust
-*/
-
-pub struct PermutationsReplacementIter<I> {
-    items: Vec<I>,
-    permutation: Vec<usize>,
-    group_len: usize,
-    finished: bool,
-}
-
-impl<I: Copy> PermutationsReplacementIter<I> {
-    fn increment_permutation(&mut self) -> bool {
-        let mut idx = 0;
-
-        loop {
-            if idx >= self.permutation.len() {
-                return true;
-            }
-
-            self.permutation[idx] += 1;
-
-            if self.permutation[idx] >= self.items.len() {
-                self.permutation[idx] = 0;
-                idx += 1;
-            } else {
-                return false;
-            }
-        }
-    }
-
-    fn build_vec(&self) -> Vec<I> {
-        let mut vec = Vec::with_capacity(self.group_len);
-
-        for idx in &self.permutation {
-            vec.push(self.items[*idx]);
-        }
-
-        vec
-    }
-}
-
-impl<I: Copy> Iterator for PermutationsReplacementIter<I> {
-    type Item = Vec<I>;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.finished {
-            return None;
-        }
-
-        let item = self.build_vec();
-
-        if self.increment_permutation() {
-            self.finished = true;
-        }
-
-        Some(item)
-    }
-}
-
-pub trait ToPermutationsWithReplacement {
-    type Iter;
-    fn permutations_with_replacement(self, group_len: usize) -> Self::Iter;
-}
-
-impl<I: Iterator> ToPermutationsWithReplacement for I {
-    type Iter = PermutationsReplacementIter<<I as Iterator>::Item>;
-
-    fn permutations_with_replacement(self, group_len: usize) -> Self::Iter {
-        let items = self.collect::<Vec<_>>();
-        PermutationsReplacementIter {
-            permutation: vec![0; group_len],
-            group_len,
-            finished: group_len == 0 || items.len() == 0,
-            items,
-        }
-    }
-}
@@ -1,215 +1,359 @@
 use std::fs;
-use std::collections::VecDeque;
+use std::collections::{BTreeMap, VecDeque};
+use crate::moves::Move;
 
-struct File {
-    id: usize,
-    block: Block,
+#[derive(Clone, Debug)]
+pub struct File {
+    pub id: usize,
+    pub block: Block,
 }
 
-struct Block {
-    start_index: usize,
-    num_blocks: usize,
+#[derive(Clone, Debug)]
+pub struct Block {
+    pub start_index: usize,
+    pub num_blocks: usize,
 }
 
+#[derive(Clone)]
 pub struct DiskManager {
     disk_string: String,
-    disk: Vec<usize>,
-    free_indexes: VecDeque<usize>,
-    free_blocks: VecDeque<Block>,
+    // the disk is stored as runs of blocks (extents) instead of block by block, so big disks don't need a
+    // usize for every block. start index -> the file in that run, ordered by start
+    extents: BTreeMap<usize, File>,
+    num_blocks: usize,
+    // start index -> number of blocks for every run of free blocks, ordered by start
+    free_blocks: BTreeMap<usize, usize>,
+    // indexed by file id
     files_on_disk: VecDeque<File>,
+    // every move made since the disk was read, in order
+    move_log: Vec<Move>,
 }
 
-enum DISK_STRING_POSITION {
-    FILE_SIZE,
-    FREE_SPACE,
+enum DiskStringPosition {
+    FileSize,
+    FreeSpace,
+}
+
+fn char_to_usize(c: char) -> Option<usize> {
+    c.to_digit(10).map(|digit| digit as usize)
+}
+
+/*
+the sizes in a disk string, alternating file size then free space.
+the normal format is one digit per size like 2333133121414131402, for disks with bigger files or gaps
+the sizes can be written out in full separated by commas like 2,3,3,3,13,3
+leading and trailing whitespace (like the newline at the end of the file) is ignored
+ */
+fn parse_sizes(disk_string: &str) -> Result<Vec<usize>, String> {
+    let disk_string = disk_string.trim();
+
+    if disk_string.contains(',') {
+        // a single size is written with a comma after it so it isn't read back as digits
+        let disk_string = disk_string.strip_suffix(',').unwrap_or(disk_string);
+        let mut sizes = vec![];
+        for (entry, size) in disk_string.split(',').enumerate() {
+            let size = size.trim();
+            if size.is_empty() {
+                return Err(format!("missing size in entry {}", entry));
+            }
+            match size.parse::<usize>() {
+                Ok(size) => sizes.push(size),
+                Err(_e) => return Err(format!("invalid size {:?} in entry {}", size, entry)),
+            }
+        }
+        return Ok(sizes);
+    }
+
+    let mut sizes = vec![];
+    for (position, c) in disk_string.chars().enumerate() {
+        match char_to_usize(c) {
+            Some(size) => sizes.push(size),
+            None if c.is_whitespace() => return Err(format!("unexpected whitespace {:?} at position {}", c, position)),
+            None => return Err(format!("invalid character {:?} at position {}", c, position)),
+        }
+    }
+    Ok(sizes)
 }
 
-fn char_to_usize(c: char) -> usize {
-    // assuming this is ASCII and it is a numeric character subtract 48 from the decimal value of the character
-    (c as usize) - 48usize
+// adds the same block to the diagram count times, with the separator between blocks
+fn write_blocks(diagram: &mut String, block: &str, count: usize, separator: &str) {
+    for _ in 0..count {
+        if !diagram.is_empty() {
+            diagram.push_str(separator);
+        }
+        diagram.push_str(block);
+    }
 }
 
 impl DiskManager {
 
     pub fn new() -> DiskManager {
         DiskManager { disk_string: "".to_string(), 
-                    disk: vec![], 
-                    free_indexes: VecDeque::new(),
-                    free_blocks: VecDeque::new(),
+                    extents: BTreeMap::new(),
+                    num_blocks: 0,
+                    free_blocks: BTreeMap::new(),
                     files_on_disk: VecDeque::new(),
+                    move_log: vec![],
                     }
     }
 
-    pub fn parse_disk_string(&mut self) {
-        let disk_string_options: Vec<DISK_STRING_POSITION> = vec![DISK_STRING_POSITION::FILE_SIZE, DISK_STRING_POSITION::FREE_SPACE];
+    pub fn parse_disk_string(&mut self) -> Result<(), String> {
+        let disk_string_options: Vec<DiskStringPosition> = vec![DiskStringPosition::FileSize, DiskStringPosition::FreeSpace];
 
-        let disk_characters: Vec<char> = self.disk_string.chars().collect();
+        let sizes = parse_sizes(&self.disk_string)?;
         let mut file_id: usize = 0;
 
-        for i in 0..disk_characters.len() {
+        for (i, size) in sizes.into_iter().enumerate() {
             match disk_string_options[i%disk_string_options.len()] {
-                DISK_STRING_POSITION::FILE_SIZE => {
-                    let file_size = char_to_usize(disk_characters[i]);
-                    println!("file_size         {} {} {}", self.disk.len(), file_id, file_size);
-
-                    self.files_on_disk.push_back(File{id: file_id, 
-                                                    block: Block{start_index: self.disk.len(), num_blocks: file_size}});
+                DiskStringPosition::FileSize => {
+                    let file_size = size;
+                    println!("file_size         {} {} {}", self.num_blocks, file_id, file_size);
 
-                    for _ in 0..file_size {
-                        self.disk.push(file_id);
+                    let file = File{id: file_id, block: Block{start_index: self.num_blocks, num_blocks: file_size}};
+                    if file_size > 0 {
+                        self.extents.insert(self.num_blocks, file.clone());
                     }
+                    self.files_on_disk.push_back(file);
+
+                    self.num_blocks += file_size;
 
                     file_id += 1;
                 },
-                DISK_STRING_POSITION::FREE_SPACE => {
-                    let free_space_blocks = char_to_usize(disk_characters[i]);
+                DiskStringPosition::FreeSpace => {
+                    let free_space_blocks = size;
 
                     if free_space_blocks == 0 {
                         continue;
                     }
-                    //println!("free_space_blocks {} {}", disk_characters[i], free_space_blocks);
+                    //println!("free_space_blocks {} {}", size, free_space_blocks);
 
                     // empty space here
-                    println!("free_space_blocks {} {}", self.disk.len(), free_space_blocks);
+                    println!("free_space_blocks {} {}", self.num_blocks, free_space_blocks);
 
 
-                    self.free_blocks.push_back(Block{start_index: self.disk.len(), num_blocks: free_space_blocks});
+                    // an empty file between two runs of free space doesn't split them up
+                    self.coalesce_free_block(Block{start_index: self.num_blocks, num_blocks: free_space_blocks});
 
-
-                    for _ in 0..free_space_blocks {
-                        self.free_indexes.push_back(self.disk.len());
-                        self.disk.push(usize::MAX);
-                    }
+                    self.num_blocks += free_space_blocks;
 
                 }
             }
         }
 
+        Ok(())
     }
 
     fn coalesce_free_block(&mut self, block: Block) {
-        // before adding a block try to add it to an existing free block
-
-        for existing_block in &mut self.free_blocks {
-            if block.start_index + block.num_blocks == existing_block.start_index {
-                // this block precedes the existing block, adjust the existing block
-                existing_block.start_index = block.start_index;
-                existing_block.num_blocks += block.num_blocks;
-                return;
-            } else if existing_block.start_index + existing_block.num_blocks == block.start_index {
-                // this block is after the existing block, adjust the existing block
-                existing_block.num_blocks += block.num_blocks;
-                return;
+        // before adding a block try to add it to the free blocks either side of it
+        let mut start_index = block.start_index;
+        let mut num_blocks = block.num_blocks;
+
+        if let Some(following) = self.free_blocks.remove(&(start_index + num_blocks)) {
+            // this block precedes an existing block, take that one over
+            num_blocks += following;
+        }
+
+        if let Some((preceding_start, preceding_blocks)) = self.free_blocks.range(..start_index).next_back() {
+            if preceding_start + preceding_blocks == start_index {
+                // this block is after an existing block, extend that one instead
+                start_index = *preceding_start;
+                num_blocks += preceding_blocks;
             }
         }
 
-        // no free bock was found adjacent to this block
-        self.free_blocks.push_back(block);
+        self.free_blocks.insert(start_index, num_blocks);
+    }
 
+    fn take_free_blocks(&mut self, start_index: usize, num_blocks: usize) {
+        // split the free block containing these blocks around them
+        let (&block_start, &block_blocks) = self.free_blocks.range(..=start_index).next_back()
+            .expect("taking blocks that are not free");
+        self.free_blocks.remove(&block_start);
+
+        if start_index > block_start {
+            self.free_blocks.insert(block_start, start_index - block_start);
+        }
+        let block_end = block_start + block_blocks;
+        if start_index + num_blocks < block_end {
+            self.free_blocks.insert(start_index + num_blocks, block_end - start_index - num_blocks);
+        }
     }
 
-    pub fn compact_files(&mut self) {
-        while self.files_on_disk.len() > 0 {
-            let file = self.files_on_disk.pop_back().unwrap();
+    pub fn num_blocks(&self) -> usize {
+        self.num_blocks
+    }
 
-            for i in 0..self.free_blocks.len() {
-                let block = self.free_blocks.get_mut(i).unwrap();
-                if file.block.start_index < block.start_index {
-                    // do not try to move the block backwards
-                    continue;
-                }
+    // true if all num_blocks blocks from start_index on are in one extent of this file
+    pub fn holds(&self, file_id: usize, start_index: usize, num_blocks: usize) -> bool {
+        match self.extents.range(..=start_index).next_back() {
+            Some((extent_start, extent)) => {
+                extent.id == file_id && start_index + num_blocks <= extent_start + extent.block.num_blocks
+            },
+            None => false,
+        }
+    }
 
-                if file.block.num_blocks <= block.num_blocks {
-                    // found free blocks this file can fit in
+    // true if all num_blocks blocks from start_index on are free
+    pub fn is_free(&self, start_index: usize, num_blocks: usize) -> bool {
+        match self.free_blocks.range(..=start_index).next_back() {
+            Some((block_start, block_blocks)) => start_index + num_blocks <= block_start + block_blocks,
+            None => false,
+        }
+    }
 
-                    // move file here
-                    for j in block.start_index..block.start_index+file.block.num_blocks {
-                        self.disk[j] = file.id;
-                    }
+    pub fn move_log(&self) -> &[Move] {
+        &self.move_log
+    }
 
-                    // free up file indexes
-                    for j in file.block.start_index..file.block.start_index+file.block.num_blocks {
-                        self.disk[j] = usize::MAX;
-                    }
+    // every run of blocks belonging to one file, keyed by where the run starts
+    pub fn extents(&self) -> &BTreeMap<usize, File> {
+        &self.extents
+    }
 
-                    // update block that was free
-                    block.start_index += file.block.num_blocks;
-                    block.num_blocks -= file.block.num_blocks;
-                    if block.num_blocks == 0 {
-                        // no more free room here
-                        self.free_blocks.remove(i);
-                    }
+    // where each file was put when the disk was read and where whole file moves have put it since,
+    // moving part of a file splits it up so it is not tracked here
+    pub fn files(&self) -> &VecDeque<File> {
+        &self.files_on_disk
+    }
 
-                    self.coalesce_free_block(Block{start_index: file.block.start_index, num_blocks: file.block.num_blocks});
+    pub fn free_blocks(&self) -> &BTreeMap<usize, usize> {
+        &self.free_blocks
+    }
 
-                    break;
-                }
+    // move a whole file so it starts at start_index, every block it lands on must be free
+    pub fn move_file(&mut self, file_id: usize, start_index: usize) {
+        let file = self.files_on_disk[file_id].clone();
+
+        self.move_blocks(file.block.start_index, file.block.num_blocks, start_index);
+        self.files_on_disk[file_id].block.start_index = start_index;
+    }
+
+    // move num_blocks blocks starting at from, which must all be in one extent, into the free blocks starting at to
+    pub fn move_blocks(&mut self, from: usize, num_blocks: usize, to: usize) {
+        if num_blocks == 0 {
+            return;
+        }
+
+        let (&extent_start, extent) = self.extents.range(..=from).next_back().expect("moving blocks that are free");
+        let file_id = extent.id;
+        let extent_end = extent_start + extent.block.num_blocks;
+        assert!(from + num_blocks <= extent_end, "moving blocks from more than one extent");
+
+        // cut the moved blocks out of their extent
+        self.extents.remove(&extent_start);
+        if from > extent_start {
+            self.insert_extent(file_id, extent_start, from - extent_start);
+        }
+        if from + num_blocks < extent_end {
+            self.insert_extent(file_id, from + num_blocks, extent_end - from - num_blocks);
+        }
+
+        self.take_free_blocks(to, num_blocks);
+        self.coalesce_free_block(Block{start_index: from, num_blocks});
+        self.move_log.push(Move{file_id, from, to, num_blocks});
+
+        // join the moved blocks up with the same file's blocks either side of where they landed
+        let mut start_index = to;
+        let mut run_blocks = num_blocks;
+        if let Some(following) = self.extents.get(&(to + num_blocks)) {
+            if following.id == file_id {
+                run_blocks += following.block.num_blocks;
+                self.extents.remove(&(to + num_blocks));
+            }
+        }
+        if let Some((&preceding_start, preceding)) = self.extents.range(..to).next_back() {
+            if preceding.id == file_id && preceding_start + preceding.block.num_blocks == to {
+                start_index = preceding_start;
+                run_blocks += preceding.block.num_blocks;
             }
         }
+        self.insert_extent(file_id, start_index, run_blocks);
     }
 
-    pub fn compact_blocks(&mut self) {
-        // start at the end of the disk and start shifting blocks over
+    fn insert_extent(&mut self, file_id: usize, start_index: usize, num_blocks: usize) {
+        self.extents.insert(start_index, File{id: file_id, block: Block{start_index, num_blocks}});
+    }
 
-        let mut new_free_space = VecDeque::new();
+    pub fn get_from_file(&mut self, file_name: &str) {
+        self.disk_string = fs::read_to_string(file_name).expect("Error reading file");
+    }
 
-        for i in (0..self.disk.len()).rev() {
-            let block_id = self.disk[i];
-            if block_id == usize::MAX {
-                // no need to move free blocks
-                continue;
-            }
+    // u128 since the checksum of a disk with huge extents can go past a u64
+    pub fn checksum(&self) -> u128 {
+        let mut sum: u128 = 0;
+        for extent in self.extents.values() {
+            // id * (start + start+1 + ... + start+n-1), free blocks are not in any extent so they are never summed
+            let start = extent.block.start_index as u128;
+            let n = extent.block.num_blocks as u128;
+            sum += (extent.id as u128) * (n * start + n * (n - 1) / 2);
+        }
 
-            let free_block_index: usize;
+        sum
+    }
 
-            if self.free_indexes.len() == 0 {
-                free_block_index = new_free_space.pop_front().unwrap();
-            } else {
-                free_block_index = self.free_indexes.pop_front().unwrap();
+    /*
+    the disk written back out in the format parse_disk_string reads, using the comma separated format if any run is
+    too big for one digit. files are numbered by where they are on the disk when it is read back in, so ids can
+    change, and a file that has been split up comes back as one file per extent
+     */
+    pub fn to_disk_string(&self) -> String {
+        let mut sizes: Vec<usize> = vec![];
+        let mut position = 0;
+
+        for (&start_index, extent) in &self.extents {
+            let free_space_blocks = start_index - position;
+            if sizes.is_empty() && free_space_blocks > 0 {
+                // free space at the very start goes after an empty file
+                sizes.push(0);
             }
-            
-            if free_block_index > i {
-                // no more free spaces below our current index
-                return;
+            if !sizes.is_empty() {
+                sizes.push(free_space_blocks);
             }
-            // move the block to the free space
-            self.disk[free_block_index] = block_id;
 
-            // the old block index is now free
-            self.disk[i] = usize::MAX;
-            // keep pushing new free spaces to the front as they come available since they will be the new lowest index
-            new_free_space.push_front(i); 
+            sizes.push(extent.block.num_blocks);
+            position = start_index + extent.block.num_blocks;
+        }
 
-            println!("move {} from {} to {}", block_id, i, free_block_index);
+        if self.num_blocks > position {
+            if sizes.is_empty() {
+                sizes.push(0);
+            }
+            sizes.push(self.num_blocks - position);
         }
-    }
 
-    pub fn get_from_file(&mut self, file_name: &str) {
-        self.disk_string = fs::read_to_string(file_name).expect("Error reading file");
+        if sizes.iter().all(|size| *size <= 9) {
+            sizes.iter().map(|size| size.to_string()).collect()
+        } else if sizes.len() == 1 {
+            // without a comma a lone size like 15 would be read back as two single digit sizes
+            format!("{},", sizes[0])
+        } else {
+            sizes.iter().map(|size| size.to_string()).collect::<Vec<_>>().join(",")
+        }
     }
 
-    fn checksum(&self) -> u64 {
-        let mut sum: u64 = 0;
-        for i in 0..self.disk.len() {
-            if self.disk[i] == usize::MAX {
-                // do not sum free blocks
-                continue;
-            }
-            sum += (self.disk[i] as u64) * (i as u64);
+    /*
+    every block on the disk, the file id or . when it is free, like 0099811188827773336446555566..............
+    when there are more than 10 files each block is written out in full and separated with commas
+     */
+    pub fn to_diagram(&self) -> String {
+        let separator = if self.files_on_disk.len() <= 10 { "" } else { "," };
+        let mut diagram = String::with_capacity(self.num_blocks);
+
+        let mut position = 0;
+        for (&start_index, extent) in &self.extents {
+            write_blocks(&mut diagram, ".", start_index - position, separator);
+            write_blocks(&mut diagram, &extent.id.to_string(), extent.block.num_blocks, separator);
+            position = start_index + extent.block.num_blocks;
         }
+        write_blocks(&mut diagram, ".", self.num_blocks - position, separator);
 
-        return sum;
+        diagram
     }
 
     pub fn print(&self) {
-        println!("{}", self.disk_string);
-
-        println!("{:?}", self.disk);
+        println!("{}", self.to_disk_string());
 
         println!("checksum {}", self.checksum());
 
     }
 }
-
-
-
@@ -0,0 +1,139 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+/*
+* Index over the free spans on the disk for finding the leftmost (or rightmost) span a file fits in.
+*
+* Spans are kept in disk order and a segment tree above them holds the largest span in each subtree,
+* so finding the first span with room for n blocks walks down one path instead of scanning every span.
+*/
+
+pub struct FreeSpaceIndex {
+    starts: Vec<usize>,
+    // number of leaves in the tree, a power of two at least as big as the number of spans
+    leaves: usize,
+    // tree[1] is the root, the children of i are 2i and 2i+1 and span s is the leaf at leaves + s
+    tree: Vec<usize>,
+}
+
+impl FreeSpaceIndex {
+    // spans are (start index, number of blocks) and must be given in disk order
+    pub fn new(spans: impl Iterator<Item = (usize, usize)>) -> FreeSpaceIndex {
+        let (starts, sizes): (Vec<usize>, Vec<usize>) = spans.unzip();
+
+        let leaves = sizes.len().next_power_of_two();
+        let mut tree = vec![0; 2 * leaves];
+        tree[leaves..leaves + sizes.len()].copy_from_slice(&sizes);
+        for node in (1..leaves).rev() {
+            tree[node] = tree[2 * node].max(tree[2 * node + 1]);
+        }
+
+        FreeSpaceIndex { starts, leaves, tree }
+    }
+
+    // the leftmost span with at least num_blocks free, if any. empty files never need a span
+    pub fn find_first(&self, num_blocks: usize) -> Option<usize> {
+        if num_blocks == 0 || self.tree[1] < num_blocks {
+            return None;
+        }
+
+        let mut node = 1;
+        while node < self.leaves {
+            // go left whenever the left half has room, that's where the lower disk indexes are
+            node = if self.tree[2 * node] >= num_blocks { 2 * node } else { 2 * node + 1 };
+        }
+
+        Some(node - self.leaves)
+    }
+
+    // the rightmost span with at least num_blocks free, if any
+    pub fn find_last(&self, num_blocks: usize) -> Option<usize> {
+        if num_blocks == 0 || self.tree[1] < num_blocks {
+            return None;
+        }
+
+        let mut node = 1;
+        while node < self.leaves {
+            node = if self.tree[2 * node + 1] >= num_blocks { 2 * node + 1 } else { 2 * node };
+        }
+
+        Some(node - self.leaves)
+    }
+
+    pub fn start(&self, span: usize) -> usize {
+        self.starts[span]
+    }
+
+    pub fn size(&self, span: usize) -> usize {
+        self.tree[self.leaves + span]
+    }
+
+    // fill the first num_blocks of a span
+    pub fn take(&mut self, span: usize, num_blocks: usize) {
+        self.starts[span] += num_blocks;
+        self.shrink(span, num_blocks);
+    }
+
+    // fill the last num_blocks of a span
+    pub fn take_end(&mut self, span: usize, num_blocks: usize) {
+        self.shrink(span, num_blocks);
+    }
+
+    fn shrink(&mut self, span: usize, num_blocks: usize) {
+        let mut node = self.leaves + span;
+        self.tree[node] -= num_blocks;
+        while node > 1 {
+            node /= 2;
+            self.tree[node] = self.tree[2 * node].max(self.tree[2 * node + 1]);
+        }
+    }
+}
+
+/*
+* Free spans grouped by size, for finding the smallest or largest span a file fits in.
+* the leftmost span wins when several are the same size
+*/
+
+pub struct FreeSizeIndex {
+    // size -> start index of every span that size
+    by_size: BTreeMap<usize, BTreeSet<usize>>,
+}
+
+impl FreeSizeIndex {
+    // spans are (start index, number of blocks)
+    pub fn new(spans: impl Iterator<Item = (usize, usize)>) -> FreeSizeIndex {
+        let mut index = FreeSizeIndex { by_size: BTreeMap::new() };
+        for (start, size) in spans {
+            index.insert(start, size);
+        }
+        index
+    }
+
+    // the smallest span that fits num_blocks and starts before the given index, as (start, size)
+    pub fn best_fit(&self, num_blocks: usize, before: usize) -> Option<(usize, usize)> {
+        self.by_size.range(num_blocks..)
+            .find_map(|(size, starts)| starts.first().filter(|start| **start < before).map(|start| (*start, *size)))
+    }
+
+    // the largest span that fits num_blocks and starts before the given index, as (start, size)
+    pub fn worst_fit(&self, num_blocks: usize, before: usize) -> Option<(usize, usize)> {
+        self.by_size.range(num_blocks..).rev()
+            .find_map(|(size, starts)| starts.first().filter(|start| **start < before).map(|start| (*start, *size)))
+    }
+
+    // fill the first num_blocks of the span at start
+    pub fn take(&mut self, start: usize, size: usize, num_blocks: usize) {
+        if let Some(starts) = self.by_size.get_mut(&size) {
+            starts.remove(&start);
+            if starts.is_empty() {
+                self.by_size.remove(&size);
+            }
+        }
+        self.insert(start + num_blocks, size - num_blocks);
+    }
+
+    fn insert(&mut self, start: usize, size: usize) {
+        if size > 0 {
+            self.by_size.entry(size).or_default().insert(start);
+        }
+    }
+}
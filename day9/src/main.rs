@@ -1,7 +1,21 @@
-
 use std::env;
+use std::fs;
+#[path = "../../utils/src/args.rs"] mod args;
+use args::flag_value;
 mod disk;
+mod free_space;
+mod moves;
+mod stats;
+mod strategy;
+mod validate;
 use disk::DiskManager;
+use moves::{read_move_log, write_move_log, Move};
+use stats::FragmentationStats;
+use strategy::{all_strategies, parse_strategy, Strategy};
+use validate::validate;
+
+const USAGE: &str = "usage: day9 [file] [--strategy blocks|first-fit|best-fit|worst-fit|end|all] [--save <file>] [--validate]
+            [--move-log <file>] [--replay <move log>] [--stats] [--diagram]";
 
 fn main() {
     env::set_var("RUST_BACKTRACE", "1");
@@ -9,33 +23,163 @@ fn main() {
     let cmd_args: Vec<String> = env::args().collect();
 
     let mut source_file = "example".to_string();
-
-    if cmd_args.len() > 1 {
-        source_file = cmd_args[1].clone();
+    // first-fit is part 2, blocks is part 1
+    let mut strategy_name = "first-fit".to_string();
+    let mut save_file: Option<String> = None;
+    let mut check = false;
+    let mut move_log_file: Option<String> = None;
+    let mut replay_file: Option<String> = None;
+    let mut show_stats = false;
+    // one character per block, so only when asked for
+    let mut show_diagram = false;
+
+    let mut i = 1;
+    while i < cmd_args.len() {
+        match cmd_args[i].as_str() {
+            "--strategy" => {
+                let Some(name) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                strategy_name = name.to_string();
+            },
+            "--save" => {
+                // write the compacted disk out as a disk map
+                let Some(file) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                save_file = Some(file.to_string());
+            },
+            "--validate" => check = true,
+            "--stats" => show_stats = true,
+            "--diagram" => show_diagram = true,
+            "--move-log" => {
+                let Some(file) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                move_log_file = Some(file.to_string());
+            },
+            "--replay" => {
+                // apply the moves from a move log instead of running the strategy
+                let Some(file) = flag_value(&cmd_args, &mut i, USAGE) else { return; };
+                replay_file = Some(file.to_string());
+            },
+            arg => source_file = arg.to_string(),
+        }
+        i += 1;
     }
 
     let mut disk_manager: DiskManager = DiskManager::new();
 
     disk_manager.get_from_file(&source_file);
 
-    disk_manager.parse_disk_string();
-
+    if let Err(e) = disk_manager.parse_disk_string() {
+        println!("{}: {}", source_file, e);
+        return;
+    }
 
-    disk_manager.print();
+    if show_stats {
+        print!("before: {}", FragmentationStats::from_disk(&disk_manager));
+    }
 
-    //disk_manager.compact_blocks(); // part 1
+    if strategy_name == "all" {
+        // run every strategy on its own copy of the disk so they can be compared
+        for strategy in all_strategies() {
+            let disk_manager = compact(strategy.as_ref(), disk_manager.clone());
+            if check {
+                print_problems(&disk_manager, strategy.as_ref());
+            }
+            if show_stats {
+                print!("{}: {}", strategy.name(), FragmentationStats::from_disk(&disk_manager));
+            }
+        }
+        return;
+    }
 
-    disk_manager.compact_files(); // part 2
+    let Some(strategy) = parse_strategy(&strategy_name) else {
+        println!("unknown strategy {}", strategy_name);
+        return;
+    };
 
     disk_manager.print();
+    if show_diagram {
+        println!("{}", disk_manager.to_diagram());
+    }
 
+    let disk_manager = match replay_file {
+        Some(replay_file) => {
+            let moves = match read_move_log(&replay_file) {
+                Ok(moves) => moves,
+                Err(e) => {
+                    println!("{}: {}", replay_file, e);
+                    return;
+                },
+            };
+            match replay(disk_manager, &moves, check, strategy.as_ref()) {
+                Some(disk_manager) => disk_manager,
+                None => return,
+            }
+        },
+        None => compact(strategy.as_ref(), disk_manager),
+    };
 
-    
-}
+    disk_manager.print();
+    if show_diagram {
+        println!("{}", disk_manager.to_diagram());
+    }
 
+    if check {
+        print_problems(&disk_manager, strategy.as_ref());
+    }
+
+    if show_stats {
+        print!("after: {}", FragmentationStats::from_disk(&disk_manager));
+    }
 
+    if let Some(move_log_file) = move_log_file {
+        write_move_log(&move_log_file, disk_manager.move_log());
+    }
 
+    if let Some(save_file) = save_file {
+        fs::write(&save_file, disk_manager.to_disk_string() + "\n").expect("Error writing file");
+    }
+}
 
+fn compact(strategy: &dyn Strategy, mut disk_manager: DiskManager) -> DiskManager {
+    let moves = strategy.compact(&mut disk_manager);
+    println!("{}: {} moves checksum {}", strategy.name(), moves, disk_manager.checksum());
+    disk_manager
+}
 
+// apply each move in turn, checking the disk after every step when validating. None if a move can't be made
+fn replay(mut disk_manager: DiskManager, moves: &[Move], check: bool, strategy: &dyn Strategy) -> Option<DiskManager> {
+    for (step, mv) in moves.iter().enumerate() {
+        if !disk_manager.holds(mv.file_id, mv.from, mv.num_blocks) {
+            println!("step {}: {} but those blocks are not all file {}", step + 1, mv, mv.file_id);
+            return None;
+        }
+        if !disk_manager.is_free(mv.to, mv.num_blocks) {
+            println!("step {}: {} but there is no free space there", step + 1, mv);
+            return None;
+        }
+
+        disk_manager.move_blocks(mv.from, mv.num_blocks, mv.to);
+        println!("step {}: {} checksum {}", step + 1, mv, disk_manager.checksum());
+
+        if check {
+            let problems = validate(&disk_manager, strategy);
+            if !problems.is_empty() {
+                println!("disk is invalid after step {}", step + 1);
+                for problem in problems {
+                    println!("{}", problem);
+                }
+                return None;
+            }
+        }
+    }
 
+    Some(disk_manager)
+}
 
+fn print_problems(disk_manager: &DiskManager, strategy: &dyn Strategy) {
+    let problems = validate(disk_manager, strategy);
+    if problems.is_empty() {
+        println!("{}: disk is valid", strategy.name());
+    }
+    for problem in problems {
+        println!("{}: {}", strategy.name(), problem);
+    }
+}
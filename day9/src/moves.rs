@@ -0,0 +1,54 @@
+use std::fmt;
+use std::fs;
+
+// one step of compacting a disk, num_blocks blocks of a file going from one place to another
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Move {
+    pub file_id: usize,
+    pub from: usize,
+    pub to: usize,
+    pub num_blocks: usize,
+}
+
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "move {} from {} to {} blocks {}", self.file_id, self.from, self.to, self.num_blocks)
+    }
+}
+
+impl Move {
+    // reads a line written by Display, like "move 9 from 40 to 2 blocks 2"
+    pub fn parse(line: &str) -> Option<Move> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["move", file_id, "from", from, "to", to, "blocks", num_blocks] => Some(Move {
+                file_id: file_id.parse().ok()?,
+                from: from.parse().ok()?,
+                to: to.parse().ok()?,
+                num_blocks: num_blocks.parse().ok()?,
+            }),
+            _ => None,
+        }
+    }
+}
+
+pub fn write_move_log(file_name: &str, moves: &[Move]) {
+    let lines: Vec<String> = moves.iter().map(|mv| mv.to_string() + "\n").collect();
+    fs::write(file_name, lines.concat()).expect("Error writing file");
+}
+
+pub fn read_move_log(file_name: &str) -> Result<Vec<Move>, String> {
+    let log = fs::read_to_string(file_name).map_err(|e| e.to_string())?;
+
+    let mut moves = vec![];
+    for (line_number, line) in log.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match Move::parse(line) {
+            Some(mv) => moves.push(mv),
+            None => return Err(format!("unreadable move on line {}: {}", line_number + 1, line)),
+        }
+    }
+    Ok(moves)
+}
@@ -0,0 +1,63 @@
+use std::fmt;
+use crate::disk::DiskManager;
+
+// how broken up the files and free space on a disk are
+pub struct FragmentationStats {
+    pub free_extents: usize,
+    pub largest_free_extent: usize,
+    pub fragmented_files: usize,
+    // extents per file, leaving out empty files since they don't take up any
+    pub average_fragments: f64,
+    // free extents counted by size, bucket i holds the sizes from 2^i to 2^(i+1) - 1
+    pub free_extent_histogram: Vec<usize>,
+}
+
+impl FragmentationStats {
+    pub fn from_disk(disk: &DiskManager) -> FragmentationStats {
+        let mut free_extent_histogram: Vec<usize> = vec![];
+        for num_blocks in disk.free_blocks().values() {
+            let bucket = num_blocks.ilog2() as usize;
+            if bucket >= free_extent_histogram.len() {
+                free_extent_histogram.resize(bucket + 1, 0);
+            }
+            free_extent_histogram[bucket] += 1;
+        }
+
+        let mut fragments_per_file = vec![0; disk.files().len()];
+        for extent in disk.extents().values() {
+            fragments_per_file[extent.id] += 1;
+        }
+        let files_with_blocks = fragments_per_file.iter().filter(|fragments| **fragments > 0).count();
+
+        FragmentationStats {
+            free_extents: disk.free_blocks().len(),
+            largest_free_extent: disk.free_blocks().values().copied().max().unwrap_or(0),
+            fragmented_files: fragments_per_file.iter().filter(|fragments| **fragments > 1).count(),
+            average_fragments: if files_with_blocks == 0 {
+                0.0
+            } else {
+                disk.extents().len() as f64 / files_with_blocks as f64
+            },
+            free_extent_histogram,
+        }
+    }
+}
+
+impl fmt::Display for FragmentationStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "free extents {} largest free extent {} fragmented files {} average fragments per file {:.3}",
+            self.free_extents, self.largest_free_extent, self.fragmented_files, self.average_fragments)?;
+
+        for (bucket, count) in self.free_extent_histogram.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let smallest = 1usize << bucket;
+            let largest = smallest - 1 + smallest;
+            let sizes = if smallest == largest { smallest.to_string() } else { format!("{}-{}", smallest, largest) };
+            writeln!(f, "  free extents of size {:>11} {}", sizes, count)?;
+        }
+
+        Ok(())
+    }
+}
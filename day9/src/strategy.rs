@@ -0,0 +1,191 @@
+use crate::disk::DiskManager;
+use crate::free_space::{FreeSizeIndex, FreeSpaceIndex};
+
+/*
+* A way of defragmenting the disk. Files are only ever moved into the free space there was before compacting:
+* when files are moved one at a time starting from the far end, the space each move frees up is always on the
+* wrong side of the files still waiting to move
+*/
+pub trait Strategy {
+    fn name(&self) -> String;
+
+    // compacts the disk and returns how many moves it took
+    fn compact(&self, disk: &mut DiskManager) -> usize;
+
+    // false if the strategy can leave a file split up into pieces
+    fn keeps_files_whole(&self) -> bool {
+        true
+    }
+
+    // false if the strategy moves files towards the end of the disk instead
+    fn moves_left(&self) -> bool {
+        true
+    }
+}
+
+// move blocks one at a time from the end of the disk into the first free block until there are no gaps (part 1)
+pub struct Blocks;
+
+impl Strategy for Blocks {
+    fn name(&self) -> String {
+        "blocks".to_string()
+    }
+
+    fn compact(&self, disk: &mut DiskManager) -> usize {
+        let mut moves = 0;
+
+        // fill the first free run with as much of the last extent as fits, every block counts as a move
+        loop {
+            let Some((&free_start, &free_blocks)) = disk.free_blocks().first_key_value() else {
+                return moves;
+            };
+            let Some((&extent_start, extent)) = disk.extents().last_key_value() else {
+                return moves;
+            };
+            if free_start > extent_start {
+                // no more free spaces below the last file block
+                return moves;
+            }
+
+            let num_blocks = free_blocks.min(extent.block.num_blocks);
+            disk.move_blocks(extent_start + extent.block.num_blocks - num_blocks, num_blocks, free_start);
+            moves += num_blocks;
+        }
+    }
+
+    fn keeps_files_whole(&self) -> bool {
+        false
+    }
+}
+
+// move whole files, highest id first, into the leftmost free span they fit in (part 2)
+pub struct FirstFit;
+
+impl Strategy for FirstFit {
+    fn name(&self) -> String {
+        "first-fit".to_string()
+    }
+
+    fn compact(&self, disk: &mut DiskManager) -> usize {
+        let mut free_space = FreeSpaceIndex::new(disk.free_blocks().iter().map(|(start, blocks)| (*start, *blocks)));
+        let mut moves = 0;
+
+        for file_id in (0..disk.files().len()).rev() {
+            let file = disk.files()[file_id].clone();
+
+            let Some(span) = free_space.find_first(file.block.num_blocks) else {
+                // nowhere big enough for this file
+                continue;
+            };
+
+            let start_index = free_space.start(span);
+            if file.block.start_index < start_index {
+                // do not try to move the block backwards
+                continue;
+            }
+
+            free_space.take(span, file.block.num_blocks);
+            disk.move_file(file.id, start_index);
+            moves += 1;
+        }
+
+        moves
+    }
+}
+
+// move whole files, highest id first, into the smallest free span to their left they fit in
+pub struct BestFit;
+
+impl Strategy for BestFit {
+    fn name(&self) -> String {
+        "best-fit".to_string()
+    }
+
+    fn compact(&self, disk: &mut DiskManager) -> usize {
+        compact_by_size(disk, FreeSizeIndex::best_fit)
+    }
+}
+
+// move whole files, highest id first, into the largest free span to their left, leaving the biggest gap behind
+pub struct WorstFit;
+
+impl Strategy for WorstFit {
+    fn name(&self) -> String {
+        "worst-fit".to_string()
+    }
+
+    fn compact(&self, disk: &mut DiskManager) -> usize {
+        compact_by_size(disk, FreeSizeIndex::worst_fit)
+    }
+}
+
+fn compact_by_size(disk: &mut DiskManager, fit: fn(&FreeSizeIndex, usize, usize) -> Option<(usize, usize)>) -> usize {
+    let mut free_space = FreeSizeIndex::new(disk.free_blocks().iter().map(|(start, blocks)| (*start, *blocks)));
+    let mut moves = 0;
+
+    for file_id in (0..disk.files().len()).rev() {
+        let file = disk.files()[file_id].clone();
+        if file.block.num_blocks == 0 {
+            // empty files have nothing to move
+            continue;
+        }
+
+        let Some((start_index, size)) = fit(&free_space, file.block.num_blocks, file.block.start_index) else {
+            continue;
+        };
+
+        free_space.take(start_index, size, file.block.num_blocks);
+        disk.move_file(file.id, start_index);
+        moves += 1;
+    }
+
+    moves
+}
+
+// the mirror image of first fit: move whole files, lowest id first, as far right as they fit, so the free space
+// ends up at the start of the disk
+pub struct MoveToEnd;
+
+impl Strategy for MoveToEnd {
+    fn name(&self) -> String {
+        "end".to_string()
+    }
+
+    fn compact(&self, disk: &mut DiskManager) -> usize {
+        let mut free_space = FreeSpaceIndex::new(disk.free_blocks().iter().map(|(start, blocks)| (*start, *blocks)));
+        let mut moves = 0;
+
+        for file_id in 0..disk.files().len() {
+            let file = disk.files()[file_id].clone();
+
+            let Some(span) = free_space.find_last(file.block.num_blocks) else {
+                continue;
+            };
+
+            if free_space.start(span) < file.block.start_index {
+                // the rightmost space it fits in is behind it
+                continue;
+            }
+
+            // fill the span from its end
+            let start_index = free_space.start(span) + free_space.size(span) - file.block.num_blocks;
+            free_space.take_end(span, file.block.num_blocks);
+            disk.move_file(file.id, start_index);
+            moves += 1;
+        }
+
+        moves
+    }
+
+    fn moves_left(&self) -> bool {
+        false
+    }
+}
+
+pub fn parse_strategy(name: &str) -> Option<Box<dyn Strategy>> {
+    all_strategies().into_iter().find(|strategy| strategy.name() == name)
+}
+
+pub fn all_strategies() -> Vec<Box<dyn Strategy>> {
+    vec![Box::new(Blocks), Box::new(FirstFit), Box::new(BestFit), Box::new(WorstFit), Box::new(MoveToEnd)]
+}
@@ -0,0 +1,65 @@
+use crate::disk::DiskManager;
+use crate::strategy::Strategy;
+
+/*
+* Checks a disk is still consistent after compacting it, returning a description of everything that is wrong:
+* - the files and free runs cover the whole disk without overlapping, and free runs next to each other were joined
+* - every file still has as many blocks as it started with, in one piece unless the strategy splits files
+* - every move went the way the strategy says files should go
+*/
+pub fn validate(disk: &DiskManager, strategy: &dyn Strategy) -> Vec<String> {
+    let mut problems: Vec<String> = vec![];
+
+    // (start index, number of blocks, file id or None when free) for every run on the disk
+    let mut runs: Vec<(usize, usize, Option<usize>)> = disk.extents().values()
+        .map(|extent| (extent.block.start_index, extent.block.num_blocks, Some(extent.id)))
+        .chain(disk.free_blocks().iter().map(|(start, blocks)| (*start, *blocks, None)))
+        .collect();
+    runs.sort();
+
+    let mut position = 0;
+    let mut previous_free = false;
+    for (start_index, num_blocks, file_id) in &runs {
+        if *num_blocks == 0 {
+            problems.push(format!("empty run at {}", start_index));
+        }
+        if *start_index > position {
+            problems.push(format!("blocks {} to {} are not a file or free", position, start_index - 1));
+        } else if *start_index < position {
+            problems.push(format!("run at {} overlaps the run before it", start_index));
+        }
+        if previous_free && file_id.is_none() {
+            problems.push(format!("free run at {} was not joined to the free run before it", start_index));
+        }
+        position = position.max(start_index + num_blocks);
+        previous_free = file_id.is_none();
+    }
+    if position != disk.num_blocks() {
+        problems.push(format!("runs end at {} but the disk has {} blocks", position, disk.num_blocks()));
+    }
+
+    let mut blocks_per_file = vec![0; disk.files().len()];
+    let mut pieces_per_file = vec![0; disk.files().len()];
+    for extent in disk.extents().values() {
+        blocks_per_file[extent.id] += extent.block.num_blocks;
+        pieces_per_file[extent.id] += 1;
+    }
+    for file in disk.files() {
+        if blocks_per_file[file.id] != file.block.num_blocks {
+            problems.push(format!("file {} has {} blocks instead of {}", file.id, blocks_per_file[file.id], file.block.num_blocks));
+        }
+        if strategy.keeps_files_whole() && pieces_per_file[file.id] > 1 {
+            problems.push(format!("file {} is split into {} pieces", file.id, pieces_per_file[file.id]));
+        }
+    }
+
+    for mv in disk.move_log() {
+        if strategy.moves_left() && mv.to > mv.from {
+            problems.push(format!("{} moved right", mv));
+        } else if !strategy.moves_left() && mv.to < mv.from {
+            problems.push(format!("{} moved left", mv));
+        }
+    }
+
+    problems
+}
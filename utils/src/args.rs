@@ -0,0 +1,14 @@
+/*
+* Command line helpers shared by the days that take flags
+*/
+
+// moves i on to the value after the flag at i. when the flag is the last argument there is no value, so this
+// prints the usage and returns None
+pub fn flag_value<'a>(cmd_args: &'a [String], i: &mut usize, usage: &str) -> Option<&'a str> {
+    *i += 1;
+    if *i >= cmd_args.len() {
+        println!("{} needs a value", cmd_args[*i - 1]);
+        println!("{}", usage);
+    }
+    cmd_args.get(*i).map(String::as_str)
+}
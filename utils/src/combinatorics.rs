@@ -0,0 +1,444 @@
+/*
+* Iterators over arrangements of items taken from another iterator.
+*
+* Every iterator here yields a new Vec per group through Iterator::next, or can be driven with next_ref
+* which writes each group into a buffer that is reused between calls, so nothing is allocated per group.
+* Asking for groups of 0 items, or groups from no items, yields nothing.
+*
+* Permutations with replacement credit Locke:
+  https://stackoverflow.com/questions/71420176/permutations-with-replacement-in-rust
+*/
+
+// copies items[index] for each index into the buffer, replacing whatever was there
+fn fill_buffer<I: Clone>(buffer: &mut Vec<I>, items: &[I], indexes: &[usize]) {
+    buffer.clear();
+    buffer.extend(indexes.iter().map(|index| items[*index].clone()));
+}
+
+/*
+* Permutations with replacement: every item can be picked for every position, n^k groups
+*/
+
+pub struct PermutationsReplacementIter<I> {
+    items: Vec<I>,
+    permutation: Vec<usize>,
+    buffer: Vec<I>,
+    finished: bool,
+}
+
+impl<I: Clone> PermutationsReplacementIter<I> {
+    fn increment_permutation(&mut self) -> bool {
+        let mut idx = 0;
+
+        loop {
+            if idx >= self.permutation.len() {
+                return true;
+            }
+
+            self.permutation[idx] += 1;
+
+            if self.permutation[idx] >= self.items.len() {
+                self.permutation[idx] = 0;
+                idx += 1;
+            } else {
+                return false;
+            }
+        }
+    }
+
+    pub fn next_ref(&mut self) -> Option<&[I]> {
+        if self.finished {
+            return None;
+        }
+
+        fill_buffer(&mut self.buffer, &self.items, &self.permutation);
+
+        if self.increment_permutation() {
+            self.finished = true;
+        }
+
+        Some(&self.buffer)
+    }
+}
+
+impl<I: Clone> Iterator for PermutationsReplacementIter<I> {
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().map(|group| group.to_vec())
+    }
+}
+
+pub trait ToPermutationsWithReplacement {
+    type Iter;
+    fn permutations_with_replacement(self, group_len: usize) -> Self::Iter;
+}
+
+impl<I: Iterator> ToPermutationsWithReplacement for I {
+    type Iter = PermutationsReplacementIter<<I as Iterator>::Item>;
+
+    fn permutations_with_replacement(self, group_len: usize) -> Self::Iter {
+        let items = self.collect::<Vec<_>>();
+        PermutationsReplacementIter {
+            permutation: vec![0; group_len],
+            buffer: Vec::with_capacity(group_len),
+            finished: group_len == 0 || items.is_empty(),
+            items,
+        }
+    }
+}
+
+/*
+* Permutations without replacement: ordered groups of k different items, n! / (n - k)! groups in lexicographic order
+*/
+
+pub struct PermutationsIter<I> {
+    items: Vec<I>,
+    indexes: Vec<usize>,
+    // how many more swaps each position has before it wraps back around
+    cycles: Vec<usize>,
+    group_len: usize,
+    buffer: Vec<I>,
+    first: bool,
+    finished: bool,
+}
+
+impl<I: Clone> PermutationsIter<I> {
+    fn increment_permutation(&mut self) -> bool {
+        let n = self.items.len();
+
+        for i in (0..self.group_len).rev() {
+            self.cycles[i] -= 1;
+
+            if self.cycles[i] == 0 {
+                // every item has had a turn in this position, put them back in order and carry to the left
+                self.indexes[i..].rotate_left(1);
+                self.cycles[i] = n - i;
+            } else {
+                let j = n - self.cycles[i];
+                self.indexes.swap(i, j);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    pub fn next_ref(&mut self) -> Option<&[I]> {
+        if self.finished {
+            return None;
+        }
+
+        if self.first {
+            self.first = false;
+        } else if self.increment_permutation() {
+            self.finished = true;
+            return None;
+        }
+
+        fill_buffer(&mut self.buffer, &self.items, &self.indexes[..self.group_len]);
+        Some(&self.buffer)
+    }
+}
+
+impl<I: Clone> Iterator for PermutationsIter<I> {
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().map(|group| group.to_vec())
+    }
+}
+
+pub trait ToPermutations {
+    type Iter;
+    fn permutations(self, group_len: usize) -> Self::Iter;
+}
+
+impl<I: Iterator> ToPermutations for I {
+    type Iter = PermutationsIter<<I as Iterator>::Item>;
+
+    fn permutations(self, group_len: usize) -> Self::Iter {
+        let items = self.collect::<Vec<_>>();
+        let n = items.len();
+        PermutationsIter {
+            indexes: (0..n).collect(),
+            cycles: (0..group_len).map(|i| n.saturating_sub(i)).collect(),
+            group_len,
+            buffer: Vec::with_capacity(group_len),
+            first: true,
+            finished: group_len == 0 || group_len > n,
+            items,
+        }
+    }
+}
+
+/*
+* Combinations: groups of k different items where order doesn't matter, n! / (k! (n - k)!) groups
+*/
+
+pub struct CombinationsIter<I> {
+    items: Vec<I>,
+    indexes: Vec<usize>,
+    buffer: Vec<I>,
+    first: bool,
+    finished: bool,
+}
+
+impl<I: Clone> CombinationsIter<I> {
+    fn increment_combination(&mut self) -> bool {
+        let n = self.items.len();
+        let k = self.indexes.len();
+
+        // find the rightmost index that can still move right
+        let Some(i) = (0..k).rev().find(|i| self.indexes[*i] != i + n - k) else {
+            return true;
+        };
+
+        self.indexes[i] += 1;
+        for j in i + 1..k {
+            self.indexes[j] = self.indexes[j - 1] + 1;
+        }
+
+        false
+    }
+
+    pub fn next_ref(&mut self) -> Option<&[I]> {
+        if self.finished {
+            return None;
+        }
+
+        if self.first {
+            self.first = false;
+        } else if self.increment_combination() {
+            self.finished = true;
+            return None;
+        }
+
+        fill_buffer(&mut self.buffer, &self.items, &self.indexes);
+        Some(&self.buffer)
+    }
+}
+
+impl<I: Clone> Iterator for CombinationsIter<I> {
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().map(|group| group.to_vec())
+    }
+}
+
+pub trait ToCombinations {
+    type Iter;
+    fn combinations(self, group_len: usize) -> Self::Iter;
+}
+
+impl<I: Iterator> ToCombinations for I {
+    type Iter = CombinationsIter<<I as Iterator>::Item>;
+
+    fn combinations(self, group_len: usize) -> Self::Iter {
+        let items = self.collect::<Vec<_>>();
+        CombinationsIter {
+            indexes: (0..group_len).collect(),
+            buffer: Vec::with_capacity(group_len),
+            first: true,
+            finished: group_len == 0 || group_len > items.len(),
+            items,
+        }
+    }
+}
+
+/*
+* Combinations with replacement: groups of k items where an item can be picked more than once and order doesn't matter
+*/
+
+pub struct CombinationsReplacementIter<I> {
+    items: Vec<I>,
+    indexes: Vec<usize>,
+    buffer: Vec<I>,
+    first: bool,
+    finished: bool,
+}
+
+impl<I: Clone> CombinationsReplacementIter<I> {
+    fn increment_combination(&mut self) -> bool {
+        let last_item = self.items.len() - 1;
+
+        let Some(i) = (0..self.indexes.len()).rev().find(|i| self.indexes[*i] != last_item) else {
+            return true;
+        };
+
+        // indexes never go down from left to right, so everything after i starts again from the new value
+        let value = self.indexes[i] + 1;
+        for index in &mut self.indexes[i..] {
+            *index = value;
+        }
+
+        false
+    }
+
+    pub fn next_ref(&mut self) -> Option<&[I]> {
+        if self.finished {
+            return None;
+        }
+
+        if self.first {
+            self.first = false;
+        } else if self.increment_combination() {
+            self.finished = true;
+            return None;
+        }
+
+        fill_buffer(&mut self.buffer, &self.items, &self.indexes);
+        Some(&self.buffer)
+    }
+}
+
+impl<I: Clone> Iterator for CombinationsReplacementIter<I> {
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().map(|group| group.to_vec())
+    }
+}
+
+pub trait ToCombinationsWithReplacement {
+    type Iter;
+    fn combinations_with_replacement(self, group_len: usize) -> Self::Iter;
+}
+
+impl<I: Iterator> ToCombinationsWithReplacement for I {
+    type Iter = CombinationsReplacementIter<<I as Iterator>::Item>;
+
+    fn combinations_with_replacement(self, group_len: usize) -> Self::Iter {
+        let items = self.collect::<Vec<_>>();
+        CombinationsReplacementIter {
+            indexes: vec![0; group_len],
+            buffer: Vec::with_capacity(group_len),
+            first: true,
+            finished: group_len == 0 || items.is_empty(),
+            items,
+        }
+    }
+}
+
+/*
+* Cartesian product: one item from each of several lists, with the last list changing fastest
+*/
+
+pub struct CartesianProductIter<I> {
+    lists: Vec<Vec<I>>,
+    indexes: Vec<usize>,
+    buffer: Vec<I>,
+    finished: bool,
+}
+
+impl<I: Clone> CartesianProductIter<I> {
+    fn increment_product(&mut self) -> bool {
+        for i in (0..self.lists.len()).rev() {
+            self.indexes[i] += 1;
+
+            if self.indexes[i] < self.lists[i].len() {
+                return false;
+            }
+            self.indexes[i] = 0;
+        }
+
+        true
+    }
+
+    pub fn next_ref(&mut self) -> Option<&[I]> {
+        if self.finished {
+            return None;
+        }
+
+        self.buffer.clear();
+        for (list, index) in self.lists.iter().zip(&self.indexes) {
+            self.buffer.push(list[*index].clone());
+        }
+
+        if self.increment_product() {
+            self.finished = true;
+        }
+
+        Some(&self.buffer)
+    }
+}
+
+impl<I: Clone> Iterator for CartesianProductIter<I> {
+    type Item = Vec<I>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ref().map(|group| group.to_vec())
+    }
+}
+
+pub trait ToCartesianProduct {
+    type Iter;
+    fn cartesian_product(self) -> Self::Iter;
+}
+
+impl<I: Iterator> ToCartesianProduct for I
+where I::Item: IntoIterator, {
+    type Iter = CartesianProductIter<<<I as Iterator>::Item as IntoIterator>::Item>;
+
+    fn cartesian_product(self) -> Self::Iter {
+        let lists: Vec<Vec<_>> = self.map(|list| list.into_iter().collect()).collect();
+        CartesianProductIter {
+            indexes: vec![0; lists.len()],
+            buffer: Vec::with_capacity(lists.len()),
+            finished: lists.is_empty() || lists.iter().any(|list| list.is_empty()),
+            lists,
+        }
+    }
+}
+
+/*
+* k-subsets of {0, 1, .. n-1} as bit masks, bit i set when i is in the subset. n can be at most 64.
+* uses Gosper's hack to step to the next larger number with the same number of bits set
+*/
+
+pub struct KSubsetsIter {
+    current: u64,
+    // first mask that is too big, or None when n is 64 and every mask fits
+    limit: Option<u64>,
+    finished: bool,
+}
+
+impl Iterator for KSubsetsIter {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+
+        let subset = self.current;
+
+        let lowest_bit = self.current & self.current.wrapping_neg();
+        match self.current.checked_add(lowest_bit) {
+            Some(ripple) => {
+                self.current = ripple | (((self.current ^ ripple) >> 2) / lowest_bit);
+                if self.limit.is_some_and(|limit| self.current >= limit) {
+                    self.finished = true;
+                }
+            },
+            // the top bit carried out, so that was the last one
+            None => self.finished = true,
+        }
+
+        Some(subset)
+    }
+}
+
+pub fn k_subsets(n: usize, k: usize) -> KSubsetsIter {
+    assert!(n <= 64, "k_subsets works on at most 64 items");
+
+    let limit = if n == 64 { None } else { Some(1u64 << n) };
+    let current = if k == 64 { u64::MAX } else { (1u64 << k) - 1 };
+
+    KSubsetsIter {
+        current,
+        limit,
+        finished: k == 0 || k > n,
+    }
+}
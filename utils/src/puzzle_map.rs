@@ -179,8 +179,12 @@ impl PuzzleMap {
         if cmd_args.len() > 1 {
             file_name = &cmd_args[1];
         }
-    
 
+        self.read_file(file_name);
+    }
+
+    #[allow(dead_code)]
+    pub fn read_file(&mut self, file_name: &str) {
         println!("get area map from file {}", file_name);
     
         if let Ok(lines) = read_lines(file_name.to_string()) {
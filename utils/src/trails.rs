@@ -1,25 +1,80 @@
 
+// shared with the other days, so its older style is left alone
+#[allow(clippy::needless_return, clippy::redundant_field_names, clippy::lines_filter_map_ok,
+        clippy::println_empty_string, clippy::unnecessary_to_owned, clippy::for_kv_map)]
 mod puzzle_map;
 use puzzle_map::PuzzleMap;
-use puzzle_map::Coordinates;
+pub use puzzle_map::Coordinates;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::cmp::Reverse;
 use std::env::Args;
 
 type PeakMap = HashMap<Coordinates, HashSet<Coordinates>>;
 type RatingsMap = HashMap<Coordinates, usize>;
 
+/*
+* What counts as a trail. the default is the day 10 puzzle: start at 0, climb exactly 1 each step up, down, left or
+* right and finish at 9. anything on the map that isn't a digit (like .) can't be walked on
+*/
+#[derive(Copy, Clone)]
+pub struct ClimbingRules {
+    // how much the height can change in one step, both ends included
+    pub min_delta: i32,
+    pub max_delta: i32,
+    pub start_height: u32,
+    pub goal_height: u32,
+    pub diagonals: bool,
+}
+
+impl Default for ClimbingRules {
+    fn default() -> ClimbingRules {
+        ClimbingRules { min_delta: 1, max_delta: 1, start_height: 0, goal_height: 9, diagonals: false }
+    }
+}
+
+impl ClimbingRules {
+    // trails are worked out one height at a time, so every step has to go the same way, up or down, and the goal
+    // has to be that way from the start
+    fn check(&self) -> Result<(), String> {
+        if self.min_delta > self.max_delta {
+            return Err(format!("height delta range {}..{} is empty", self.min_delta, self.max_delta));
+        }
+        if self.min_delta <= 0 && self.max_delta >= 0 {
+            return Err(format!("height delta range {}..{} includes 0, every step has to climb or every step has to descend",
+                self.min_delta, self.max_delta));
+        }
+        if self.climbs() && self.goal_height <= self.start_height {
+            return Err(format!("trails climb, so the goal height {} has to be above the start height {}",
+                self.goal_height, self.start_height));
+        }
+        if !self.climbs() && self.goal_height >= self.start_height {
+            return Err(format!("trails descend, so the goal height {} has to be below the start height {}",
+                self.goal_height, self.start_height));
+        }
+        Ok(())
+    }
+
+    fn climbs(&self) -> bool {
+        self.min_delta > 0
+    }
+}
+
 pub struct TrailFinder {
     area_map: PuzzleMap,
+    rules: ClimbingRules,
     trail_head_peaks: PeakMap,
     trail_head_ratings: RatingsMap,
+    // number of trails from each cell to a peak, indexed by row * width + col
+    trails_to_peaks: Vec<usize>,
 }
 
 impl TrailFinder {
     #[inline]
     #[must_use]
     pub fn new() -> TrailFinder {
-        TrailFinder { area_map: PuzzleMap::new(), trail_head_peaks: PeakMap::new(), trail_head_ratings: RatingsMap::new() }
+        TrailFinder { area_map: PuzzleMap::new(), rules: ClimbingRules::default(), trail_head_peaks: PeakMap::new(), trail_head_ratings: RatingsMap::new(), trails_to_peaks: vec![] }
     }
 
     #[allow(dead_code)]
@@ -27,9 +82,21 @@ impl TrailFinder {
         self.area_map.get_from_file(args);
     }
 
+    #[allow(dead_code)]
+    pub fn set_rules(&mut self, rules: ClimbingRules) -> Result<(), String> {
+        rules.check()?;
+        self.rules = rules;
+        Ok(())
+    }
+
+    #[allow(dead_code)]
+    pub fn read_file(&mut self, file_name: &str) {
+        self.area_map.read_file(file_name);
+    }
+
     fn get_total_score(&self) -> usize {
         let mut total_score: usize = 0;
-        for (_, peaks) in &self.trail_head_peaks {
+        for peaks in self.trail_head_peaks.values() {
             total_score += peaks.len();
         }
 
@@ -39,7 +106,7 @@ impl TrailFinder {
     fn get_total_rating(&self) -> usize {
         let mut total_ratings: usize = 0;
 
-        for (_, rating) in &self.trail_head_ratings {
+        for rating in self.trail_head_ratings.values() {
             total_ratings += rating;
         }
 
@@ -55,57 +122,203 @@ impl TrailFinder {
 
     }
 
-    fn step(&mut self, trail_head: &Coordinates, last_coord: &Coordinates, next_coord_option: &Option<Coordinates>) {
-        if *next_coord_option == None {
-            return;
+    // every coordinate next to this one that is on the map, including the diagonals if the rules allow them
+    fn neighbours(&self, coord: &Coordinates) -> Vec<Coordinates> {
+        let mut neighbours = vec![coord.up(1), coord.down(1), coord.left(1), coord.right(1)];
+        if self.rules.diagonals {
+            neighbours.extend([
+                coord.up(1).and_then(|up| up.left(1)),
+                coord.up(1).and_then(|up| up.right(1)),
+                coord.down(1).and_then(|down| down.left(1)),
+                coord.down(1).and_then(|down| down.right(1)),
+            ]);
         }
 
-        let next_coord: Coordinates = next_coord_option.unwrap();
+        neighbours.into_iter()
+            .flatten()
+            .filter(|next_coord| self.area_map.coordinates_are_in_bounds(next_coord))
+            .collect()
+    }
 
-        if !self.area_map.coordinates_are_in_bounds(&next_coord) {
-            return;
-        }
+    // None for anything that isn't a digit, which can't be walked on
+    fn height(&self, coord: &Coordinates) -> Option<u32> {
+        self.area_map[coord].to_digit(10)
+    }
 
-        if self.area_map[&next_coord] as u8 == self.area_map[last_coord] as u8 + 1 {
-            self.find_peak(trail_head, &next_coord);
-        }
+    // false for anything off the map as well
+    pub fn is_trail_head(&self, coord: &Coordinates) -> bool {
+        self.area_map.coordinates_are_in_bounds(coord) && self.height(coord) == Some(self.rules.start_height)
+    }
+
+    fn is_peak(&self, coord: &Coordinates) -> bool {
+        self.height(coord) == Some(self.rules.goal_height)
+    }
+
+    fn index(&self, coord: &Coordinates) -> usize {
+        coord.row * self.area_map.width() + coord.col
+    }
+
+    fn can_step(&self, last_coord: &Coordinates, next_coord: &Coordinates) -> bool {
+        let (Some(last_height), Some(next_height)) = (self.height(last_coord), self.height(next_coord)) else {
+            return false;
+        };
+        let delta = next_height as i32 - last_height as i32;
+        self.rules.min_delta <= delta && delta <= self.rules.max_delta
     }
 
-    fn find_peak(&mut self, trail_head: &Coordinates, coord: &Coordinates) {
-        match self.area_map[coord] {
-            '9' => {
+    /*
+    every step of a trail climbs (or every step descends), so working back from the peaks one height at a time
+    means every cell a trail can step to has been worked out before the cells that step onto it. each cell gets the
+    number of trails from it to a peak (its rating) and the set of peaks it can reach (its score) by adding up its
+    neighbours', instead of walking every trail from every trail head
+     */
+    #[allow(dead_code)]
+    pub fn find_all_trail_head_peaks(&mut self) {
+        let rows = self.area_map.len();
+        let cols = if rows == 0 { 0 } else { self.area_map.width() };
+        let index = |coord: &Coordinates| coord.row * cols + coord.col;
+
+        let mut cells: Vec<Coordinates> = vec![];
+        for row in 0..rows {
+            for col in 0..self.area_map[row].len() {
+                let coord = Coordinates::new(row, col);
+                if self.height(&coord).is_some() {
+                    cells.push(coord);
+                }
+            }
+        }
+        if self.rules.climbs() {
+            cells.sort_by_key(|coord| Reverse(self.height(coord)));
+        } else {
+            cells.sort_by_key(|coord| self.height(coord));
+        }
+
+        let mut trails_to_peaks: Vec<usize> = vec![0; rows * cols];
+        let mut reachable_peaks: Vec<HashSet<Coordinates>> = vec![HashSet::new(); rows * cols];
+
+        for coord in &cells {
+            if self.is_peak(coord) {
                 // We've reached a peak
-                self.trail_head_peaks.get_mut(trail_head).unwrap().insert(*coord);
-                *self.trail_head_ratings.get_mut(trail_head).unwrap() += 1;
-            },
-            _ => {
-                // Keep going
-                self.step(trail_head, coord, &coord.up(1));
-                self.step(trail_head, coord, &coord.down(1));
-                self.step(trail_head, coord, &coord.left(1));
-                self.step(trail_head, coord, &coord.right(1));
+                trails_to_peaks[index(coord)] = 1;
+                reachable_peaks[index(coord)].insert(*coord);
+                continue;
+            }
+
+            let mut trails = 0;
+            let mut peaks: HashSet<Coordinates> = HashSet::new();
+            for next_coord in self.neighbours(coord) {
+                if self.can_step(coord, &next_coord) {
+                    trails += trails_to_peaks[index(&next_coord)];
+                    peaks.extend(&reachable_peaks[index(&next_coord)]);
+                }
+            }
+            trails_to_peaks[index(coord)] = trails;
+            reachable_peaks[index(coord)] = peaks;
+        }
+
+        for coord in &cells {
+            if self.is_trail_head(coord) {
+                self.trail_head_peaks.insert(*coord, std::mem::take(&mut reachable_peaks[index(coord)]));
+                self.trail_head_ratings.insert(*coord, trails_to_peaks[index(coord)]);
             }
         }
+
+        self.trails_to_peaks = trails_to_peaks;
     }
 
+    // every trail head, top to bottom then left to right
+    #[allow(dead_code)]
+    pub fn trail_heads(&self) -> Vec<Coordinates> {
+        let mut trail_heads: Vec<Coordinates> = self.trail_head_peaks.keys().copied().collect();
+        trail_heads.sort_by_key(|coord| (coord.row, coord.col));
+        trail_heads
+    }
 
+    // each distinct trail from this trail head to a peak, or none if it isn't a trail head.
+    // needs find_all_trail_head_peaks to have been run first
+    #[allow(dead_code)]
+    pub fn trails(&self, trail_head: &Coordinates) -> Trails<'_> {
+        let mut stack = vec![];
+        if self.is_trail_head(trail_head) && self.trails_from(trail_head) > 0 {
+            stack.push(vec![*trail_head]);
+        }
+        Trails { trail_finder: self, stack }
+    }
+
+    fn trails_from(&self, coord: &Coordinates) -> usize {
+        self.trails_to_peaks.get(self.index(coord)).copied().unwrap_or(0)
+    }
+
+    // every cell on any trail from these trail heads, needs find_all_trail_head_peaks to have been run first
+    #[allow(dead_code)]
+    pub fn trail_cells(&self, trail_heads: &[Coordinates]) -> HashSet<Coordinates> {
+        let mut cells: HashSet<Coordinates> = HashSet::new();
+        let mut queue: VecDeque<Coordinates> = VecDeque::new();
+
+        for trail_head in trail_heads {
+            if self.is_trail_head(trail_head) && self.trails_from(trail_head) > 0 && cells.insert(*trail_head) {
+                queue.push_back(*trail_head);
+            }
+        }
 
-    fn find_trail_head_peaks(&mut self, trail_head: &Coordinates) {
-        self.trail_head_peaks.insert(*trail_head, HashSet::new());
-        self.trail_head_ratings.insert(*trail_head, 0);
+        while let Some(coord) = queue.pop_front() {
+            if self.is_peak(&coord) {
+                continue;
+            }
+            for next_coord in self.neighbours(&coord) {
+                // only follow steps that still lead to a peak
+                if self.can_step(&coord, &next_coord) && self.trails_from(&next_coord) > 0 && cells.insert(next_coord) {
+                    queue.push_back(next_coord);
+                }
+            }
+        }
 
-        self.find_peak(trail_head, trail_head);
+        cells
     }
 
+    // the map with everything but these cells blanked out with .
     #[allow(dead_code)]
-    pub fn find_all_trail_head_peaks(&mut self) {
+    pub fn render(&self, cells: &HashSet<Coordinates>) -> String {
+        let mut rendered = String::new();
         for row in 0..self.area_map.len() {
             for col in 0..self.area_map[row].len() {
-                if self.area_map[row][col] == '0' {
-                    self.find_trail_head_peaks(&Coordinates::new(row, col));
-                }
+                let coord = Coordinates::new(row, col);
+                rendered.push(if cells.contains(&coord) { self.area_map[&coord] } else { '.' });
             }
+            rendered.push('\n');
         }
+        rendered
     }
 
 }
+
+// walks every trail from one trail head depth first, only stepping onto cells that still have a trail to a peak
+pub struct Trails<'a> {
+    trail_finder: &'a TrailFinder,
+    // trails that have been started but not finished
+    stack: Vec<Vec<Coordinates>>,
+}
+
+impl Iterator for Trails<'_> {
+    type Item = Vec<Coordinates>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(trail) = self.stack.pop() {
+            let coord = *trail.last().unwrap();
+            if self.trail_finder.is_peak(&coord) {
+                return Some(trail);
+            }
+
+            // pushed in reverse so the trails come out in up, down, left, right order
+            for next_coord in self.trail_finder.neighbours(&coord).into_iter().rev() {
+                if self.trail_finder.can_step(&coord, &next_coord) && self.trail_finder.trails_from(&next_coord) > 0 {
+                    let mut next_trail = trail.clone();
+                    next_trail.push(next_coord);
+                    self.stack.push(next_trail);
+                }
+            }
+        }
+
+        None
+    }
+}